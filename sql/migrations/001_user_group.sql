@@ -0,0 +1,10 @@
+-- 用户组，限制组内用户可以使用的模型
+-- allowed_models中的每一项都是一个模型名称，支持`*`和`?`通配符，例如`gpt-4o*`
+CREATE TABLE IF NOT EXISTS user_group (
+                              id SERIAL PRIMARY KEY,
+                              name VARCHAR(50) NOT NULL UNIQUE,
+                              allowed_models TEXT[] NOT NULL DEFAULT '{}'
+);
+
+-- 用户所在的用户组，为空时用户可以使用所有模型
+ALTER TABLE "user" ADD COLUMN IF NOT EXISTS group_id INTEGER REFERENCES user_group(id);
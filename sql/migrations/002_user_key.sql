@@ -0,0 +1,38 @@
+-- 用户密钥表，一个用户可以拥有多个密钥，每个密钥可以单独设置过期时间或吊销
+CREATE TABLE IF NOT EXISTS user_key (
+                            id SERIAL PRIMARY KEY,
+                            user_id INTEGER NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+                            api_key VARCHAR(255) NOT NULL UNIQUE,
+                            label VARCHAR(50) NOT NULL DEFAULT 'default',
+                            created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                            expires_at TIMESTAMP,
+                            last_used_at TIMESTAMP,
+                            revoked_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS user_key_user_id_index ON user_key(user_id);
+
+-- 多个用户共用同一个密钥时无法迁移，中止迁移并列出这些用户，避免其中的用户静默丢失密钥
+DO $$
+DECLARE
+    duplicates TEXT;
+BEGIN
+    SELECT string_agg(format('%s (用户ID: %s)', left(api_key, 8) || '...', user_ids), ', ')
+    INTO duplicates
+    FROM (
+        SELECT api_key, string_agg(id::TEXT, ', ' ORDER BY id) AS user_ids
+        FROM "user"
+        GROUP BY api_key
+        HAVING COUNT(*) > 1
+    ) AS duplicate_keys;
+
+    IF duplicates IS NOT NULL THEN
+        RAISE EXCEPTION '以下密钥被多个用户共用，请先为这些用户更换不同的密钥后再启动: %', duplicates;
+    END IF;
+END $$;
+
+-- 将用户原有的密钥迁移到密钥表
+INSERT INTO user_key (user_id, api_key, label)
+SELECT id, api_key, 'default' FROM "user";
+
+ALTER TABLE "user" DROP COLUMN IF EXISTS api_key;
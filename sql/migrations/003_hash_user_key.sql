@@ -0,0 +1,22 @@
+-- 密钥不再明文存储，只保存密钥的前缀用于查找和展示，以及加盐后的SHA-256哈希
+-- key_hash = hex(sha256(key_salt || api_key))
+ALTER TABLE user_key ADD COLUMN IF NOT EXISTS key_prefix VARCHAR(16);
+ALTER TABLE user_key ADD COLUMN IF NOT EXISTS key_salt VARCHAR(64);
+ALTER TABLE user_key ADD COLUMN IF NOT EXISTS key_hash VARCHAR(64);
+
+-- 迁移已有的明文密钥
+UPDATE user_key
+SET key_prefix = LEFT(api_key, 11),
+    key_salt = replace(gen_random_uuid()::text, '-', '')
+WHERE key_hash IS NULL;
+
+UPDATE user_key
+SET key_hash = encode(sha256(convert_to(key_salt || api_key, 'UTF8')), 'hex')
+WHERE key_hash IS NULL;
+
+ALTER TABLE user_key ALTER COLUMN key_prefix SET NOT NULL;
+ALTER TABLE user_key ALTER COLUMN key_salt SET NOT NULL;
+ALTER TABLE user_key ALTER COLUMN key_hash SET NOT NULL;
+ALTER TABLE user_key DROP COLUMN IF EXISTS api_key;
+
+CREATE INDEX IF NOT EXISTS user_key_prefix_index ON user_key(key_prefix);
@@ -0,0 +1,6 @@
+-- 上游账户的密钥使用信封加密存储，明文的api_key会在服务启动时被加密并清空
+-- encrypted_key: 使用数据密钥加密后的api_key
+-- data_key: 使用主密钥加密后的数据密钥
+ALTER TABLE account_list ALTER COLUMN api_key DROP NOT NULL;
+ALTER TABLE account_list ADD COLUMN IF NOT EXISTS encrypted_key TEXT;
+ALTER TABLE account_list ADD COLUMN IF NOT EXISTS data_key TEXT;
@@ -0,0 +1,38 @@
+-- 用户状态或密钥变化时，通过 NOTIFY 通知服务端清除鉴权缓存，负载为用户的id
+CREATE OR REPLACE FUNCTION notify_auth_change() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_TABLE_NAME = 'user' THEN
+        PERFORM pg_notify('auth_change', OLD.id::text);
+    ELSE
+        PERFORM pg_notify('auth_change', OLD.user_id::text);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+-- 用户被冻结、解冻或删除时触发
+DROP TRIGGER IF EXISTS user_auth_change_trigger ON "user";
+CREATE TRIGGER user_auth_change_trigger
+    AFTER UPDATE OF is_active ON "user"
+    FOR EACH ROW
+    WHEN (OLD.is_active IS DISTINCT FROM NEW.is_active)
+    EXECUTE FUNCTION notify_auth_change();
+
+DROP TRIGGER IF EXISTS user_delete_auth_change_trigger ON "user";
+CREATE TRIGGER user_delete_auth_change_trigger
+    AFTER DELETE ON "user"
+    FOR EACH ROW
+    EXECUTE FUNCTION notify_auth_change();
+
+-- 密钥被吊销、修改过期时间或删除时触发，last_used_at 的更新不会触发
+DROP TRIGGER IF EXISTS user_key_auth_change_trigger ON user_key;
+CREATE TRIGGER user_key_auth_change_trigger
+    AFTER UPDATE OF revoked_at, expires_at, user_id ON user_key
+    FOR EACH ROW
+    EXECUTE FUNCTION notify_auth_change();
+
+DROP TRIGGER IF EXISTS user_key_delete_auth_change_trigger ON user_key;
+CREATE TRIGGER user_key_delete_auth_change_trigger
+    AFTER DELETE ON user_key
+    FOR EACH ROW
+    EXECUTE FUNCTION notify_auth_change();
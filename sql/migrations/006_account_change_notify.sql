@@ -0,0 +1,17 @@
+-- 上游账户被添加、修改或删除时，通过 NOTIFY 通知所有实例刷新账户池，负载为账户的id
+CREATE OR REPLACE FUNCTION notify_account_change() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        PERFORM pg_notify('account_change', OLD.id::text);
+    ELSE
+        PERFORM pg_notify('account_change', NEW.id::text);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS account_change_trigger ON account_list;
+CREATE TRIGGER account_change_trigger
+    AFTER INSERT OR UPDATE OR DELETE ON account_list
+    FOR EACH ROW
+    EXECUTE FUNCTION notify_account_change();
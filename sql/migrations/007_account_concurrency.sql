@@ -0,0 +1,2 @@
+-- 每个上游账户的最大并发数，为空时使用配置文件中的 request_concurrency_count
+ALTER TABLE account_list ADD COLUMN IF NOT EXISTS max_concurrency INTEGER CHECK (max_concurrency > 0);
@@ -0,0 +1,24 @@
+-- 用户组的优先级，数值越大越优先，大于0的用户组可以使用每个账户预留的并发
+ALTER TABLE user_group ADD COLUMN IF NOT EXISTS priority INTEGER NOT NULL DEFAULT 0;
+
+-- 优先级随鉴权信息缓存，因此用户更换分组或分组的优先级变化时也需要通知
+DROP TRIGGER IF EXISTS user_auth_change_trigger ON "user";
+CREATE TRIGGER user_auth_change_trigger
+    AFTER UPDATE OF is_active, group_id ON "user"
+    FOR EACH ROW
+    WHEN (OLD.is_active IS DISTINCT FROM NEW.is_active OR OLD.group_id IS DISTINCT FROM NEW.group_id)
+    EXECUTE FUNCTION notify_auth_change();
+
+CREATE OR REPLACE FUNCTION notify_group_change() RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM pg_notify('auth_change', id::text) FROM "user" WHERE group_id = OLD.id;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS user_group_change_trigger ON user_group;
+CREATE TRIGGER user_group_change_trigger
+    AFTER UPDATE OF priority ON user_group
+    FOR EACH ROW
+    WHEN (OLD.priority IS DISTINCT FROM NEW.priority)
+    EXECUTE FUNCTION notify_group_change();
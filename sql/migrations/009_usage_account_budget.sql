@@ -0,0 +1,49 @@
+-- 记录每次使用由哪个上游账户、哪个端点以及映射后的哪个模型完成，用于按上游账户对账
+ALTER TABLE usage_list ADD COLUMN IF NOT EXISTS account_id INTEGER REFERENCES account_list(id) ON DELETE SET NULL;
+ALTER TABLE usage_list ADD COLUMN IF NOT EXISTS endpoint VARCHAR(255);
+ALTER TABLE usage_list ADD COLUMN IF NOT EXISTS model VARCHAR(255);
+CREATE INDEX IF NOT EXISTS usage_list_account_time_index ON usage_list (account_id, timestamp);
+
+-- 每个上游账户每月的预算，为空时不限制；达到预算后记录所在月份，账户在该月内不再进入账户池
+ALTER TABLE account_list ADD COLUMN IF NOT EXISTS monthly_budget NUMERIC CHECK (monthly_budget > 0);
+ALTER TABLE account_list ADD COLUMN IF NOT EXISTS budget_exceeded_month DATE;
+
+-- 检查账户本月的花费是否达到预算，仅在状态变化时更新，从而通过 account_change 通知所有实例刷新账户池
+CREATE OR REPLACE FUNCTION check_account_budget(target INTEGER) RETURNS VOID AS $$
+DECLARE
+    current_month DATE := date_trunc('month', LOCALTIMESTAMP)::date;
+    budget NUMERIC;
+    spent NUMERIC;
+BEGIN
+    SELECT monthly_budget INTO budget FROM account_list WHERE id = target;
+
+    IF budget IS NOT NULL THEN
+        SELECT COALESCE(SUM(input_tokens * input_token_price + output_tokens * output_token_price), 0) INTO spent
+        FROM usage_list
+        WHERE account_id = target AND timestamp >= current_month;
+    END IF;
+
+    IF budget IS NOT NULL AND spent >= budget THEN
+        UPDATE account_list SET budget_exceeded_month = current_month
+        WHERE id = target AND budget_exceeded_month IS DISTINCT FROM current_month;
+    ELSE
+        UPDATE account_list SET budget_exceeded_month = NULL
+        WHERE id = target AND budget_exceeded_month IS NOT NULL;
+    END IF;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION check_usage_budget() RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.account_id IS NOT NULL THEN
+        PERFORM check_account_budget(NEW.account_id);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS usage_budget_trigger ON usage_list;
+CREATE TRIGGER usage_budget_trigger
+    AFTER INSERT ON usage_list
+    FOR EACH ROW
+    EXECUTE FUNCTION check_usage_budget();
@@ -0,0 +1,27 @@
+-- 每次使用上游收取的费用，按 model_cost 计算，未配置成本时为空
+ALTER TABLE usage_list ADD COLUMN IF NOT EXISTS cost NUMERIC;
+
+-- 账户的月度花费优先使用上游的实际费用，未配置成本时仍按向用户收取的价格估算
+CREATE OR REPLACE FUNCTION check_account_budget(target INTEGER) RETURNS VOID AS $$
+DECLARE
+    current_month DATE := date_trunc('month', LOCALTIMESTAMP)::date;
+    budget NUMERIC;
+    spent NUMERIC;
+BEGIN
+    SELECT monthly_budget INTO budget FROM account_list WHERE id = target;
+
+    IF budget IS NOT NULL THEN
+        SELECT COALESCE(SUM(COALESCE(cost, input_tokens * input_token_price + output_tokens * output_token_price)), 0) INTO spent
+        FROM usage_list
+        WHERE account_id = target AND timestamp >= current_month;
+    END IF;
+
+    IF budget IS NOT NULL AND spent >= budget THEN
+        UPDATE account_list SET budget_exceeded_month = current_month
+        WHERE id = target AND budget_exceeded_month IS DISTINCT FROM current_month;
+    ELSE
+        UPDATE account_list SET budget_exceeded_month = NULL
+        WHERE id = target AND budget_exceeded_month IS NOT NULL;
+    END IF;
+END;
+$$ LANGUAGE plpgsql;
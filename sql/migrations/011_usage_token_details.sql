@@ -0,0 +1,50 @@
+-- 命中提示词缓存的输入与推理过程的输出单独计费，两者不计入 input_tokens 与 output_tokens
+ALTER TABLE usage_list ADD COLUMN IF NOT EXISTS cached_input_tokens INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE usage_list ADD COLUMN IF NOT EXISTS cached_input_token_price NUMERIC NOT NULL DEFAULT 0;
+ALTER TABLE usage_list ADD COLUMN IF NOT EXISTS reasoning_tokens INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE usage_list ADD COLUMN IF NOT EXISTS reasoning_token_price NUMERIC NOT NULL DEFAULT 0;
+
+-- 本次使用向用户收取的总金额，由各部分的用量与单价计算
+ALTER TABLE usage_list ADD COLUMN IF NOT EXISTS amount NUMERIC GENERATED ALWAYS AS (
+    input_tokens * input_token_price
+    + cached_input_tokens * cached_input_token_price
+    + output_tokens * output_token_price
+    + reasoning_tokens * reasoning_token_price
+) STORED;
+
+-- 扣费时计入缓存输入与推理部分
+CREATE OR REPLACE FUNCTION update_user_usage() RETURNS TRIGGER AS $$
+BEGIN
+UPDATE user_usage
+SET total_input_tokens = total_input_tokens + NEW.input_tokens + NEW.cached_input_tokens,
+    total_output_tokens = total_output_tokens + NEW.output_tokens + NEW.reasoning_tokens,
+    total_purchased = total_purchased - NEW.amount
+WHERE user_id = NEW.user_id;
+
+RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION check_account_budget(target INTEGER) RETURNS VOID AS $$
+DECLARE
+    current_month DATE := date_trunc('month', LOCALTIMESTAMP)::date;
+    budget NUMERIC;
+    spent NUMERIC;
+BEGIN
+    SELECT monthly_budget INTO budget FROM account_list WHERE id = target;
+
+    IF budget IS NOT NULL THEN
+        SELECT COALESCE(SUM(COALESCE(cost, amount)), 0) INTO spent
+        FROM usage_list
+        WHERE account_id = target AND timestamp >= current_month;
+    END IF;
+
+    IF budget IS NOT NULL AND spent >= budget THEN
+        UPDATE account_list SET budget_exceeded_month = current_month
+        WHERE id = target AND budget_exceeded_month IS DISTINCT FROM current_month;
+    ELSE
+        UPDATE account_list SET budget_exceeded_month = NULL
+        WHERE id = target AND budget_exceeded_month IS NOT NULL;
+    END IF;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,7 @@
+-- 用户组的可用模型随鉴权信息缓存，因此可用模型变化时也需要通知组内用户
+DROP TRIGGER IF EXISTS user_group_change_trigger ON user_group;
+CREATE TRIGGER user_group_change_trigger
+    AFTER UPDATE OF priority, allowed_models ON user_group
+    FOR EACH ROW
+    WHEN (OLD.priority IS DISTINCT FROM NEW.priority OR OLD.allowed_models IS DISTINCT FROM NEW.allowed_models)
+    EXECUTE FUNCTION notify_group_change();
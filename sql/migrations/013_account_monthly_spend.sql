@@ -0,0 +1,58 @@
+-- 每个上游账户当月的累计花费，在写入使用记录时累加，检查预算时无需汇总 usage_list
+-- 单独建表而不放在 account_list 中，避免每次使用都触发 account_change 通知
+CREATE TABLE IF NOT EXISTS account_monthly_spend (
+    account_id INTEGER PRIMARY KEY REFERENCES account_list(id) ON DELETE CASCADE,
+    month DATE NOT NULL,
+    spent NUMERIC NOT NULL DEFAULT 0
+);
+
+-- 以本月已有的使用记录初始化
+INSERT INTO account_monthly_spend (account_id, month, spent)
+SELECT account_id, date_trunc('month', LOCALTIMESTAMP)::date, SUM(COALESCE(cost, amount))
+FROM usage_list
+WHERE account_id IS NOT NULL AND timestamp >= date_trunc('month', LOCALTIMESTAMP)
+GROUP BY account_id
+ON CONFLICT (account_id) DO NOTHING;
+
+-- 读取累计花费，跨月后上个月的花费视为0
+CREATE OR REPLACE FUNCTION check_account_budget(target INTEGER) RETURNS VOID AS $$
+DECLARE
+    current_month DATE := date_trunc('month', LOCALTIMESTAMP)::date;
+    budget NUMERIC;
+    spent NUMERIC;
+BEGIN
+    SELECT monthly_budget INTO budget FROM account_list WHERE id = target;
+
+    IF budget IS NOT NULL THEN
+        SELECT s.spent INTO spent FROM account_monthly_spend s
+        WHERE s.account_id = target AND s.month = current_month;
+        spent := COALESCE(spent, 0);
+    END IF;
+
+    IF budget IS NOT NULL AND spent >= budget THEN
+        UPDATE account_list SET budget_exceeded_month = current_month
+        WHERE id = target AND budget_exceeded_month IS DISTINCT FROM current_month;
+    ELSE
+        UPDATE account_list SET budget_exceeded_month = NULL
+        WHERE id = target AND budget_exceeded_month IS NOT NULL;
+    END IF;
+END;
+$$ LANGUAGE plpgsql;
+
+-- 累加本次使用的花费后再检查预算
+CREATE OR REPLACE FUNCTION check_usage_budget() RETURNS TRIGGER AS $$
+DECLARE
+    current_month DATE := date_trunc('month', LOCALTIMESTAMP)::date;
+BEGIN
+    IF NEW.account_id IS NOT NULL THEN
+        INSERT INTO account_monthly_spend AS s (account_id, month, spent)
+        VALUES (NEW.account_id, current_month, COALESCE(NEW.cost, NEW.amount))
+        ON CONFLICT (account_id) DO UPDATE
+        SET spent = CASE WHEN s.month = EXCLUDED.month THEN s.spent ELSE 0 END + EXCLUDED.spent,
+            month = EXCLUDED.month;
+
+        PERFORM check_account_budget(NEW.account_id);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,49 @@
+use crate::commandline::handlers::describer::{CommandDescription, CommandHandler};
+use crate::data::config::entity::runtime_data::GlobalData;
+use crate::http::client::util::account_manager::insert_account;
+use cat_macro::describe;
+use log::info;
+
+#[derive(Default)]
+pub(in crate::commandline::handlers) struct AddAccount;
+
+impl CommandHandler for AddAccount {
+    fn description(&self) -> CommandDescription {
+        describe! {
+            ["add_account" | "aa"] help "Add an account to the database and the account pool";
+            "endpoint" => "The endpoint of the account, e.g. OpenAI",
+            "key" => "The api key of the account",
+            ("proxy") => "The name of the proxy in config, if not provided, no proxy will be used.",
+        }
+    }
+
+    async fn execute(&self, global_data: &GlobalData, args: &Vec<&str>) -> anyhow::Result<()> {
+        let Some(&endpoint) = args.first() else {
+            return Err(anyhow::anyhow!("Missing endpoint"));
+        };
+
+        let Some(&key) = args.get(1) else {
+            return Err(anyhow::anyhow!("Missing key"));
+        };
+
+        // Snapshots are taken, so no lock is held while the database is accessed.
+        let config = global_data.config.read().clone();
+        let master_key = global_data.master_key.load_full();
+
+        let mut transaction = global_data.data_base.begin().await?;
+        let account_id = insert_account(
+            &mut transaction,
+            &config,
+            &master_key,
+            endpoint,
+            key,
+            args.get(2).copied(),
+        ).await?;
+        transaction.commit().await?;
+
+        // The account is put into the pool by the notification of the insert.
+        info!("Account {} of endpoint {} has been added.", account_id, endpoint);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,55 @@
+use crate::commandline::handlers::describer::{CommandDescription, CommandHandler};
+use crate::data::config::entity::runtime_data::GlobalData;
+use cat_macro::describe;
+use log::info;
+
+#[derive(Default)]
+pub(in crate::commandline::handlers) struct AddGroup;
+
+impl CommandHandler for AddGroup {
+    fn description(&self) -> CommandDescription {
+        describe! {
+            ["add_group" | "ag"] help "Add a user group, or update the allowed models if the group exists";
+            "name" => "The name of the group",
+            "models" => "The allowed models separated by comma, `*` and `?` wildcards are supported, e.g. gpt-4o*,claude-*",
+            ("priority") => "The priority of the group, the groups above 0 can use the reserved concurrency. Default is 0 for a new group.",
+        }
+    }
+
+    async fn execute(&self, global_data: &GlobalData, args: &Vec<&str>) -> anyhow::Result<()> {
+        let Some(&name) = args.first() else {
+            return Err(anyhow::anyhow!("Missing group name"));
+        };
+
+        let Some(&models) = args.get(1) else {
+            return Err(anyhow::anyhow!("Missing allowed models"));
+        };
+
+        let models = models
+            .split(',')
+            .map(|x| x.trim().to_string())
+            .filter(|x| !x.is_empty())
+            .collect::<Vec<_>>();
+
+        let priority = args.get(2).map(|x| x.parse::<i32>()).transpose()?;
+
+        let group = sqlx::query!(
+            r#"INSERT INTO user_group (name, allowed_models, priority) VALUES ($1, $2, COALESCE($3, 0))
+            ON CONFLICT (name) DO UPDATE SET
+                allowed_models = EXCLUDED.allowed_models,
+                priority = COALESCE($3, user_group.priority)
+            RETURNING id, priority"#,
+            name,
+            &models,
+            priority
+        )
+        .fetch_one(&global_data.data_base)
+        .await?;
+
+        info!(
+            "Group {}({}) has been saved, allowed models: {:?}, priority: {}",
+            name, group.id, models, group.priority
+        );
+        Ok(())
+    }
+}
@@ -1,9 +1,10 @@
 use crate::commandline::handlers::describer::{CommandDescription, CommandHandler};
 use crate::data::config::entity::runtime_data::GlobalData;
+use crate::data::database::entity::user::DataBaseUser;
+use crate::data::database::user_key::create_key;
 use cat_macro::describe;
 use log::info;
 use rust_decimal::Decimal;
-use uuid::Uuid;
 
 #[derive(Default)]
 pub(in crate::commandline::handlers) struct AddUser;
@@ -20,45 +21,34 @@ impl CommandHandler for AddUser {
     async fn execute(&self, global_data: &GlobalData, param: &Vec<&str>) -> anyhow::Result<()> {
         let key = if let Some(&first) = param.first() {
             if first.starts_with("sk-") {
-                first.to_string()
+                Some(first.to_string())
             }else {
                 return Err(anyhow::anyhow!("Invalid api key: key must start with 'sk-'"));
             }
         }else {
-            generate_key()
+            None
         };
 
-        sqlx::query!(r#"INSERT INTO "user" (api_key) VALUES ($1)"#, key)
-            .execute(&global_data.data_base)
-            .await?;
-
-        let user = sqlx::query!(r#"SELECT * FROM "user" WHERE api_key = $1"#, key)
-            .fetch_one(&global_data.data_base)
+        let mut transaction = global_data.data_base.begin().await?;
+        let user = sqlx::query_as!(DataBaseUser, r#"INSERT INTO "user" DEFAULT VALUES RETURNING *"#)
+            .fetch_one(&mut *transaction)
             .await?;
+        let (api_key, key) = create_key(&mut *transaction, user.id, key, "default", None).await?;
 
         if param.len() > 1 {
             let balance = param[1].parse::<i64>()?;
             sqlx::query!(r#"UPDATE "user_usage" SET total_purchased = $1 WHERE user_id = $2"#, Decimal::from(balance), user.id)
-                .execute(&global_data.data_base)
+                .execute(&mut *transaction)
                 .await?;
         }
+        transaction.commit().await?;
 
+        // The key is only printed to the console, never written to the log.
+        println!("Api key of user {}: {}, the key will not be shown again.", user.id, api_key);
         info!(
-            "User {:?} has been added, user: {:?}",
-            key, user
+            "User {} has been added with key {}({}...), user: {:?}.",
+            user.id, key.id, key.key_prefix, user
         );
         Ok(())
     }
 }
-
-fn generate_key() -> String {
-    let base = Uuid::new_v4().to_string().replace("-", "");
-    let extra = Uuid::new_v4().to_string();
-    format!(
-        "sk-{}{}{}{}",
-        base,
-        &extra[0..8],
-        &extra[9..13],
-        &extra[19..23]
-    )
-}
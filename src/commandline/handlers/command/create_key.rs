@@ -0,0 +1,39 @@
+use crate::commandline::handlers::describer::{CommandDescription, CommandHandler};
+use crate::data::config::entity::runtime_data::GlobalData;
+use crate::data::database::user_key::{create_key, find_user};
+use cat_macro::describe;
+use log::info;
+
+#[derive(Default)]
+pub(in crate::commandline::handlers) struct CreateKey;
+
+impl CommandHandler for CreateKey {
+    fn description(&self) -> CommandDescription {
+        describe! {
+            ["create_key" | "ck"] help "Create a new api key for a user";
+            "user" => "Any api key of the user, or the id of the user",
+            ("label") => "The label of the key, e.g. the app that uses it, `default` if not provided.",
+            ("expires_in_days") => "The key will expire after these days, never expire if not provided.",
+        }
+    }
+
+    async fn execute(&self, global_data: &GlobalData, args: &Vec<&str>) -> anyhow::Result<()> {
+        let Some(&user) = args.first() else {
+            return Err(anyhow::anyhow!("Missing user"));
+        };
+
+        let label = args.get(1).copied().unwrap_or("default");
+        let expires_in_days = args.get(2).map(|x| x.parse::<i32>()).transpose()?;
+
+        let user = find_user(&global_data.data_base, user).await?;
+        let (api_key, key) = create_key(&global_data.data_base, user.id, None, label, expires_in_days).await?;
+
+        // The key is only printed to the console, never written to the log.
+        println!("Api key {}({}): {}, the key will not be shown again.", key.label, key.id, api_key);
+        info!(
+            "Key {}({}, {}...) has been created for user {}, expires at: {:?}.",
+            key.label, key.id, key.key_prefix, user.id, key.expires_at
+        );
+        Ok(())
+    }
+}
@@ -1,5 +1,6 @@
 use crate::commandline::handlers::describer::{CommandDescription, CommandHandler};
 use crate::data::config::entity::runtime_data::GlobalData;
+use crate::data::database::user_key::find_user;
 use cat_macro::describe;
 use log::info;
 use rust_decimal::Decimal;
@@ -11,22 +12,14 @@ impl CommandHandler for EditUserBalance {
     fn description(&self) -> CommandDescription {
         describe! {
             ["edit_balance" | "eb"] help "Edit balance of a user";
-            "api_key" => "The api key of the user",
+            "user" => "Any api key of the user, or the id of the user",
             "balance" => "The new balance of the user",
         }
     }
 
     async fn execute(&self, global_data: &GlobalData, args: &Vec<&str>) -> anyhow::Result<()> {
-        let key = if let Some(&first) = args.first() {
-            if first.starts_with("sk-") {
-                first.to_string()
-            } else {
-                return Err(anyhow::anyhow!(
-                    "Invalid api key: key must start with 'sk-'"
-                ));
-            }
-        } else {
-            return Err(anyhow::anyhow!("Missing api key"));
+        let Some(&key) = args.first() else {
+            return Err(anyhow::anyhow!("Missing user"));
         };
 
         let balance = if let Some(&balance) = args.get(1) {
@@ -35,12 +28,7 @@ impl CommandHandler for EditUserBalance {
             return Err(anyhow::anyhow!("Missing balance"));
         };
 
-        let user = sqlx::query!(
-            r#"SELECT * FROM "user" WHERE api_key = $1"#,
-            key
-        )
-        .fetch_one(&global_data.data_base)
-        .await?;
+        let user = find_user(&global_data.data_base, key).await?;
 
         let origin_balance = sqlx::query!(
             r#"SELECT * FROM "user_usage" WHERE user_id = $1"#,
@@ -57,7 +45,7 @@ impl CommandHandler for EditUserBalance {
         .execute(&global_data.data_base)
         .await?;
 
-        info!("User {:?} balance has been updated, origin: {}, user: {:?}", key, origin_balance.total_purchased, user);
+        info!("User {} balance has been updated, origin: {}, user: {:?}", user.id, origin_balance.total_purchased, user);
         Ok(())
     }
 }
@@ -0,0 +1,72 @@
+use std::fs;
+
+use crate::commandline::handlers::describer::{CommandDescription, CommandHandler};
+use crate::data::config::entity::runtime_data::GlobalData;
+use crate::http::client::util::account_manager::insert_account;
+use cat_macro::describe;
+use log::info;
+
+#[derive(Default)]
+pub(in crate::commandline::handlers) struct ImportAccount;
+
+impl CommandHandler for ImportAccount {
+    fn description(&self) -> CommandDescription {
+        describe! {
+            ["import_account" | "ia"] help "Import accounts from a file, nothing is imported if any line is invalid";
+            "file" => "The path of the file, one `<endpoint> <key> [proxy]` per line, lines start with `#` are ignored",
+        }
+    }
+
+    async fn execute(&self, global_data: &GlobalData, args: &Vec<&str>) -> anyhow::Result<()> {
+        let Some(&path) = args.first() else {
+            return Err(anyhow::anyhow!("Missing file"));
+        };
+
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Unable to read {}: {}", path, e))?;
+
+        let mut accounts = Vec::new();
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            let [endpoint, key, proxy @ ..] = fields.as_slice() else {
+                return Err(anyhow::anyhow!("Line {}: missing key", index + 1));
+            };
+            if proxy.len() > 1 {
+                return Err(anyhow::anyhow!("Line {}: too many fields", index + 1));
+            }
+
+            accounts.push((index + 1, *endpoint, *key, proxy.first().copied()));
+        }
+
+        // Snapshots are taken, so no lock is held while the database is accessed.
+        let config = global_data.config.read().clone();
+        let master_key = global_data.master_key.load_full();
+
+        let mut transaction = global_data.data_base.begin().await?;
+        let mut ids = Vec::with_capacity(accounts.len());
+        for (line, endpoint, key, proxy) in accounts {
+            let id = insert_account(
+                &mut transaction,
+                &config,
+                &master_key,
+                endpoint,
+                key,
+                proxy,
+            )
+                .await
+                .map_err(|e| anyhow::anyhow!("Line {}: {}", line, e))?;
+            ids.push(id);
+        }
+        // The accounts are put into the pool by the notifications of the insert.
+        transaction.commit().await?;
+
+        info!("{} accounts have been imported: {:?}.", ids.len(), ids);
+
+        Ok(())
+    }
+}
@@ -1,5 +1,3 @@
-use std::ops::Deref;
-
 use crate::commandline::handlers::describer::{CommandDescription, CommandHandler};
 use crate::data::config::entity::runtime_data::GlobalData;
 use cat_macro::describe;
@@ -16,11 +14,17 @@ impl CommandHandler for ListAccount {
     }
 
     async fn execute(&self, global_data: &GlobalData, _: &Vec<&str>) -> anyhow::Result<()> {
-        let account = global_data.account_pool.read();
-        let account = account.deref();
-        info!("total {} accounts found.", account.len());
+        let account_pool = global_data.account_pool.load_full();
+        info!("total {} accounts found.", account_pool.len());
 
-        let accounts = sqlx::query!("SELECT * FROM account_list")
+        let accounts = sqlx::query!(
+            r#"SELECT id, is_disabled, use_proxy, endpoint, (encrypted_key IS NOT NULL) AS encrypted,
+            monthly_budget, budget_exceeded_month,
+            COALESCE(s.spent, 0) AS "month_spent!"
+            FROM account_list
+            LEFT JOIN account_monthly_spend s
+            ON s.account_id = account_list.id AND s.month = date_trunc('month', LOCALTIMESTAMP)::date"#
+        )
             .fetch_all(&global_data.data_base)
             .await?;
 
@@ -28,6 +32,25 @@ impl CommandHandler for ListAccount {
             info!("account: {:?}", account);
         }
 
+        for pool in account_pool.iter() {
+            let visitor = pool.get_visitor();
+            let rate_limit = visitor.rate_limit.info();
+            let stats = visitor.stats.info();
+            info!(
+                "account {} in pool: throttled: {}, remaining requests: {:?}/{:?}, remaining tokens: {:?}/{:?}, \
+                 ttft: {}, error rate: {:.1}% in {} attempts",
+                visitor.account_id,
+                visitor.rate_limit.is_exhausted(),
+                rate_limit.remaining_requests,
+                rate_limit.limit_requests,
+                rate_limit.remaining_tokens,
+                rate_limit.limit_tokens,
+                stats.ttft.map_or("unknown".to_string(), |x| format!("{:.0}ms", x * 1000.0)),
+                stats.error_rate * 100.0,
+                stats.attempts
+            );
+        }
+
         Ok(())
     }
 }
\ No newline at end of file
@@ -0,0 +1,39 @@
+use crate::commandline::handlers::describer::{CommandDescription, CommandHandler};
+use crate::data::config::entity::runtime_data::GlobalData;
+use crate::data::database::entity::user_group::DataBaseUserGroup;
+use cat_macro::describe;
+use log::info;
+
+#[derive(Default)]
+pub(in crate::commandline::handlers) struct ListGroup;
+
+impl CommandHandler for ListGroup {
+    fn description(&self) -> CommandDescription {
+        describe! {
+            ["list_group" | "lg"] help "List all user groups"
+        }
+    }
+
+    async fn execute(&self, global_data: &GlobalData, _: &Vec<&str>) -> anyhow::Result<()> {
+        let groups = sqlx::query_as!(DataBaseUserGroup, "SELECT * FROM user_group ORDER BY id")
+            .fetch_all(&global_data.data_base)
+            .await?;
+        info!("total {} groups found.", groups.len());
+
+        for group in groups {
+            let users = sqlx::query_scalar!(
+                r#"SELECT COUNT(*) AS "count!" FROM "user" WHERE group_id = $1"#,
+                group.id
+            )
+            .fetch_one(&global_data.data_base)
+            .await?;
+
+            info!(
+                "group: {}({}), users: {}, allowed models: {:?}, priority: {}",
+                group.name, group.id, users, group.allowed_models, group.priority
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+use crate::commandline::handlers::describer::{CommandDescription, CommandHandler};
+use crate::data::config::entity::runtime_data::GlobalData;
+use crate::data::database::entity::user_key::DataBaseUserKey;
+use crate::data::database::user_key::find_user;
+use cat_macro::describe;
+use log::info;
+
+#[derive(Default)]
+pub(in crate::commandline::handlers) struct ListKeys;
+
+impl CommandHandler for ListKeys {
+    fn description(&self) -> CommandDescription {
+        describe! {
+            ["list_keys" | "lk"] help "List all api keys of a user";
+            "user" => "Any api key of the user, or the id of the user",
+        }
+    }
+
+    async fn execute(&self, global_data: &GlobalData, args: &Vec<&str>) -> anyhow::Result<()> {
+        let Some(&user) = args.first() else {
+            return Err(anyhow::anyhow!("Missing user"));
+        };
+
+        let user = find_user(&global_data.data_base, user).await?;
+        let keys = sqlx::query_as!(
+            DataBaseUserKey,
+            "SELECT * FROM user_key WHERE user_id = $1 ORDER BY id",
+            user.id
+        )
+        .fetch_all(&global_data.data_base)
+        .await?;
+        info!("total {} keys found for user {}.", keys.len(), user.id);
+
+        for key in keys {
+            info!(
+                "key: {}...({}), label: {}, created at: {}, expires at: {:?}, last used at: {:?}, revoked at: {:?}",
+                key.key_prefix,
+                key.id,
+                key.label,
+                key.created_at,
+                key.expires_at,
+                key.last_used_at,
+                key.revoked_at
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -1,7 +1,5 @@
 use crate::commandline::handlers::describer::{CommandDescription, CommandHandler};
 use crate::data::config::entity::runtime_data::GlobalData;
-use crate::http::client::util::account_manager::load_account_from_database;
-use crate::http::client::util::counter::concurrency_pool::VecSafePool;
 use cat_macro::describe;
 use log::info;
 
@@ -28,23 +26,21 @@ impl CommandHandler for ManageAccountPool {
             return Err(anyhow::anyhow!("Missing enable"));
         };
 
-        sqlx::query!(
-            r#"UPDATE "account_list" SET is_disabled = $1 WHERE endpoint = $2"#,
+        // The other instances will refresh their pools by the notifications of the changes.
+        let accounts = sqlx::query_scalar!(
+            r#"UPDATE "account_list" SET is_disabled = $1 WHERE endpoint = $2 RETURNING id"#,
             !enable,
             endpoint
-        ).execute(&global_data.data_base)
+        ).fetch_all(&global_data.data_base)
             .await?;
 
-        if enable {
-            let visitor = load_account_from_database(&global_data.config.read(), &global_data.data_base).await?;
-            let mut pool = global_data.account_pool.write();
-            *pool = visitor.to_vec_safe_pool(global_data.config.read().request_concurrency_count);
-            info!("Endpoint {} has been enabled, now {} accounts in pool.", endpoint, pool.len());
-        }else {
-            let mut pool = global_data.account_pool.write();
-            pool.retain(|x| x.get_endpoint().to_string().as_str() != *endpoint);
-            info!("Endpoint {} has been disabled, now {} accounts in pool.", endpoint, pool.len());
-        }
+        // The pool is refreshed by the notifications of the update.
+        info!(
+            "Endpoint {} has been {}, {} accounts changed.",
+            endpoint,
+            if enable { "enabled" } else { "disabled" },
+            accounts.len()
+        );
 
         Ok(())
     }
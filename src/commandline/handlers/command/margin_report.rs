@@ -0,0 +1,70 @@
+use crate::commandline::handlers::describer::{CommandDescription, CommandHandler};
+use crate::data::config::entity::runtime_data::GlobalData;
+use cat_macro::describe;
+use log::info;
+
+/// The days reported if it is not given.
+const DEFAULT_DAYS: i32 = 7;
+
+#[derive(Default)]
+pub(in crate::commandline::handlers) struct MarginReport;
+
+impl CommandHandler for MarginReport {
+    fn description(&self) -> CommandDescription {
+        describe! {
+            ["margin_report" | "mr"] help "Show the revenue, cost and margin by day, model and endpoint";
+            ("days") => "The number of the recent days in the report, default is 7",
+        }
+    }
+
+    async fn execute(&self, global_data: &GlobalData, args: &Vec<&str>) -> anyhow::Result<()> {
+        let days = match args.first() {
+            Some(days) => days.parse::<i32>()?,
+            None => DEFAULT_DAYS,
+        };
+        if days <= 0 {
+            return Err(anyhow::anyhow!("Days must be greater than 0"));
+        }
+
+        // The requests without a known cost are counted in the revenue, but not in the margin.
+        let rows = sqlx::query!(
+            r#"SELECT
+                timestamp::date AS "day!",
+                COALESCE(model, 'unknown') AS "model!",
+                COALESCE(endpoint, 'unknown') AS "endpoint!",
+                COUNT(*) AS "requests!",
+                COUNT(cost) AS "costed!",
+                SUM(amount) AS "revenue!",
+                COALESCE(SUM(cost), 0) AS "cost!",
+                COALESCE(SUM(amount - cost), 0) AS "margin!"
+            FROM usage_list
+            WHERE timestamp >= CURRENT_DATE - $1::integer + 1
+            GROUP BY 1, 2, 3
+            ORDER BY 1 DESC, 2, 3"#,
+            days
+        )
+            .fetch_all(&global_data.data_base)
+            .await?;
+
+        if rows.is_empty() {
+            info!("No usage in the last {} days.", days);
+            return Ok(());
+        }
+
+        for row in rows {
+            info!(
+                "{} {} on {}: {} requests ({} with cost), revenue: {}, cost: {}, margin: {}",
+                row.day,
+                row.model,
+                row.endpoint,
+                row.requests,
+                row.costed,
+                row.revenue,
+                row.cost,
+                row.margin
+            );
+        }
+
+        Ok(())
+    }
+}
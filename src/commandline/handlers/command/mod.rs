@@ -4,4 +4,19 @@ pub(in crate::commandline::handlers) mod edit_balance;
 pub(in crate::commandline::handlers) mod search_balance;
 pub(in crate::commandline::handlers) mod search_user;
 pub(in crate::commandline::handlers) mod manage_account_pool;
-pub(in crate::commandline::handlers) mod list_model;
\ No newline at end of file
+pub(in crate::commandline::handlers) mod list_model;
+pub(in crate::commandline::handlers) mod reload;
+pub(in crate::commandline::handlers) mod add_group;
+pub(in crate::commandline::handlers) mod set_group;
+pub(in crate::commandline::handlers) mod list_group;
+pub(in crate::commandline::handlers) mod create_key;
+pub(in crate::commandline::handlers) mod list_keys;
+pub(in crate::commandline::handlers) mod revoke_key;
+pub(in crate::commandline::handlers) mod rotate_master_key;
+pub(in crate::commandline::handlers) mod add_account;
+pub(in crate::commandline::handlers) mod remove_account;
+pub(in crate::commandline::handlers) mod set_account;
+pub(in crate::commandline::handlers) mod import_account;
+pub(in crate::commandline::handlers) mod test_account;
+pub(in crate::commandline::handlers) mod set_account_budget;
+pub(in crate::commandline::handlers) mod margin_report;
\ No newline at end of file
@@ -0,0 +1,23 @@
+use crate::commandline::handlers::describer::{CommandDescription, CommandHandler};
+use crate::commandline::hot_reload::reload_config;
+use crate::data::config::entity::runtime_data::GlobalData;
+use cat_macro::describe;
+use log::info;
+
+#[derive(Default)]
+pub(in crate::commandline::handlers) struct Reload;
+
+impl CommandHandler for Reload {
+    fn description(&self) -> CommandDescription {
+        describe! {
+            ["reload" | "rl"] help "Reload all the config files, the old config is kept if any of them is invalid"
+        }
+    }
+
+    async fn execute(&self, global_data: &GlobalData, _: &Vec<&str>) -> anyhow::Result<()> {
+        reload_config(global_data).await?;
+        info!("Config has been reloaded.");
+
+        Ok(())
+    }
+}
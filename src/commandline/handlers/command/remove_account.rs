@@ -0,0 +1,43 @@
+use crate::commandline::handlers::describer::{CommandDescription, CommandHandler};
+use crate::data::config::entity::runtime_data::GlobalData;
+use cat_macro::describe;
+use log::info;
+
+#[derive(Default)]
+pub(in crate::commandline::handlers) struct RemoveAccount;
+
+impl CommandHandler for RemoveAccount {
+    fn description(&self) -> CommandDescription {
+        describe! {
+            ["remove_account" | "ra"] help "Remove an account from the database and the account pool";
+            "id" => "The id of the account shown in `list_account`",
+        }
+    }
+
+    async fn execute(&self, global_data: &GlobalData, args: &Vec<&str>) -> anyhow::Result<()> {
+        let Some(account_id) = args.first() else {
+            return Err(anyhow::anyhow!("Missing id"));
+        };
+        let account_id = account_id.parse::<i32>()?;
+
+        let removed = sqlx::query!(r#"DELETE FROM account_list WHERE id = $1"#, account_id)
+            .execute(&global_data.data_base)
+            .await
+            .map_err(|e| {
+                anyhow::anyhow!(
+                    "Unable to remove account {}: {}, try to disable it with `set_account` instead",
+                    account_id,
+                    e
+                )
+            })?;
+
+        if removed.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("Account {} not found", account_id));
+        }
+
+        // The account is taken out of the pool by the notification of the delete.
+        info!("Account {} has been removed.", account_id);
+
+        Ok(())
+    }
+}
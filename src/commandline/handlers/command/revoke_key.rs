@@ -0,0 +1,47 @@
+use crate::commandline::handlers::describer::{CommandDescription, CommandHandler};
+use crate::data::config::entity::runtime_data::GlobalData;
+use crate::data::database::user_key::{find_key_owner, mask_key};
+use cat_macro::describe;
+use log::info;
+
+#[derive(Default)]
+pub(in crate::commandline::handlers) struct RevokeKey;
+
+impl CommandHandler for RevokeKey {
+    fn description(&self) -> CommandDescription {
+        describe! {
+            ["revoke_key" | "rk"] help "Revoke an api key, the balance of the user is kept";
+            "key" => "The api key, or the id of the key shown in `list_keys`",
+        }
+    }
+
+    async fn execute(&self, global_data: &GlobalData, args: &Vec<&str>) -> anyhow::Result<()> {
+        let Some(&key) = args.first() else {
+            return Err(anyhow::anyhow!("Missing key"));
+        };
+
+        let key_id = if key.starts_with("sk-") {
+            find_key_owner(&global_data.data_base, key)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("Key {} not found", mask_key(key)))?
+                .key_id
+        } else {
+            key.parse::<i32>().map_err(|_| {
+                anyhow::anyhow!("Invalid key: must be an api key starts with 'sk-' or a key id")
+            })?
+        };
+
+        let revoked = sqlx::query!(
+            r#"UPDATE user_key SET revoked_at = NOW()::TIMESTAMP
+            WHERE id = $1 AND revoked_at IS NULL
+            RETURNING id, user_id"#,
+            key_id
+        )
+        .fetch_optional(&global_data.data_base)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Key {} not found or already revoked", key_id))?;
+
+        info!("Key {} of user {} has been revoked.", revoked.id, revoked.user_id);
+        Ok(())
+    }
+}
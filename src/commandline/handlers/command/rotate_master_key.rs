@@ -0,0 +1,111 @@
+use std::fs;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::sync::Arc;
+
+use crate::commandline::handlers::describer::{CommandDescription, CommandHandler};
+use crate::data::config::entity::runtime_data::GlobalData;
+use crate::data::database::account_secret::{lock_account_keys, MasterKey, MasterKeySource, MASTER_KEY_CHANGE_CHANNEL};
+use cat_macro::describe;
+use log::{info, warn};
+
+#[derive(Default)]
+pub(in crate::commandline::handlers) struct RotateMasterKey;
+
+impl CommandHandler for RotateMasterKey {
+    fn description(&self) -> CommandDescription {
+        describe! {
+            ["rotate_master_key" | "rmk"] help "Re-encrypt the data keys of all accounts with a new master key";
+            ("key") => "The new master key in base64, if not provided, a random key will be generated.",
+        }
+    }
+
+    async fn execute(&self, global_data: &GlobalData, args: &Vec<&str>) -> anyhow::Result<()> {
+        let source = global_data.master_key.load().source.clone();
+        let new_key = match args.first() {
+            Some(key) => MasterKey::from_base64(key, source.clone())?,
+            None => MasterKey::generate(source.clone())?,
+        };
+
+        let old_key = global_data.master_key.load_full();
+        let new_key = Arc::new(new_key);
+
+        // The keys are read under the lock, so no account can be added with the old key meanwhile.
+        let mut transaction = global_data.data_base.begin().await?;
+        lock_account_keys(&mut transaction, &old_key).await?;
+
+        // The other instances reload the key before the notifications of the updated accounts.
+        sqlx::query!("SELECT pg_notify($1, '')", MASTER_KEY_CHANGE_CHANNEL)
+            .execute(&mut *transaction)
+            .await?;
+
+        let accounts = sqlx::query!(
+            r#"SELECT id, data_key AS "data_key!" FROM account_list WHERE data_key IS NOT NULL"#
+        )
+            .fetch_all(&mut *transaction)
+            .await?;
+
+        for account in accounts.iter() {
+            let data_key = old_key
+                .rewrap(&account.data_key, &new_key)
+                .map_err(|e| anyhow::anyhow!("Account {}: {}", account.id, e))?;
+
+            sqlx::query!(
+                r#"UPDATE account_list SET data_key = $1 WHERE id = $2"#,
+                data_key,
+                account.id
+            )
+                .execute(&mut *transaction)
+                .await?;
+        }
+
+        // The new key file is written before the commit, so the key is never lost.
+        let new_file = match &source {
+            MasterKeySource::File(path) => {
+                let new_file = path.with_extension("key.new");
+                // Only the owner can read the key, and a file left by another rotation is not overwritten.
+                let mut options = OpenOptions::new();
+                options.write(true).create_new(true);
+                #[cfg(unix)]
+                std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+                options
+                    .open(&new_file)
+                    .and_then(|mut file| file.write_all(new_key.to_base64().as_bytes()))
+                    .map_err(|e| anyhow::anyhow!("Unable to write {}: {}", new_file.display(), e))?;
+                Some((new_file, path))
+            }
+            MasterKeySource::Env => None,
+        };
+
+        // The new key is used before the lock is released, a writer that has loaded the old key
+        // fails the check of `lock_account_keys` instead of wrapping a key with it.
+        global_data.master_key.store(new_key.clone());
+        if let Err(e) = transaction.commit().await {
+            global_data.master_key.store(old_key);
+            return Err(e.into());
+        }
+
+        match new_file {
+            Some((new_file, path)) => {
+                fs::rename(&new_file, path).map_err(|e| {
+                    anyhow::anyhow!(
+                        "The data keys have been re-encrypted, but unable to replace {} with {}: {}",
+                        path.display(),
+                        new_file.display(),
+                        e
+                    )
+                })?;
+                info!("The new master key has been written to {}.", path.display());
+            }
+            None => {
+                // The key is only printed to the console, never written to the log.
+                println!("New master key: {}", new_key.to_base64());
+                warn!("Please update GPT_CAT_MASTER_KEY with the new master key before the next restart, the other instances have to be restarted with it now.");
+            }
+        }
+
+        info!("The data keys of {} accounts have been re-encrypted.", accounts.len());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+use crate::commandline::handlers::describer::{CommandDescription, CommandHandler};
+use crate::data::config::entity::runtime_data::GlobalData;
+use cat_macro::describe;
+use log::info;
+
+#[derive(Default)]
+pub(in crate::commandline::handlers) struct SetAccount;
+
+impl CommandHandler for SetAccount {
+    fn description(&self) -> CommandDescription {
+        describe! {
+            ["set_account" | "sa"] help "Enable or disable a single account, the other accounts are not touched";
+            "id" => "The id of the account shown in `list_account`",
+            "enable" => "Enable or disable the account",
+        }
+    }
+
+    async fn execute(&self, global_data: &GlobalData, args: &Vec<&str>) -> anyhow::Result<()> {
+        let Some(account_id) = args.first() else {
+            return Err(anyhow::anyhow!("Missing id"));
+        };
+        let account_id = account_id.parse::<i32>()?;
+
+        let enable = if let Some(&enable) = args.get(1) {
+            enable.parse::<bool>()?
+        } else {
+            return Err(anyhow::anyhow!("Missing enable"));
+        };
+
+        let updated = sqlx::query!(
+            r#"UPDATE account_list SET is_disabled = $1 WHERE id = $2"#,
+            !enable,
+            account_id
+        )
+            .execute(&global_data.data_base)
+            .await?;
+
+        if updated.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("Account {} not found", account_id));
+        }
+
+        // The pool is refreshed by the notification of the update.
+        info!(
+            "Account {} has been {}.",
+            account_id,
+            if enable { "enabled" } else { "disabled" }
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,60 @@
+use crate::commandline::handlers::describer::{CommandDescription, CommandHandler};
+use crate::data::config::entity::runtime_data::GlobalData;
+use cat_macro::describe;
+use log::info;
+use rust_decimal::Decimal;
+use std::str::FromStr;
+
+#[derive(Default)]
+pub(in crate::commandline::handlers) struct SetAccountBudget;
+
+impl CommandHandler for SetAccountBudget {
+    fn description(&self) -> CommandDescription {
+        describe! {
+            ["set_account_budget" | "ab"] help "Set the monthly budget of an account, the account is taken out of the pool in the month it is reached";
+            "id" => "The id of the account shown in `list_account`",
+            ("budget") => "The budget of a month, the budget is removed if it is not given",
+        }
+    }
+
+    async fn execute(&self, global_data: &GlobalData, args: &Vec<&str>) -> anyhow::Result<()> {
+        let Some(account_id) = args.first() else {
+            return Err(anyhow::anyhow!("Missing id"));
+        };
+        let account_id = account_id.parse::<i32>()?;
+
+        let budget = match args.get(1) {
+            Some(budget) => Some(Decimal::from_str(budget)?),
+            None => None,
+        };
+        if budget.is_some_and(|x| x <= Decimal::ZERO) {
+            return Err(anyhow::anyhow!("Budget must be greater than 0"));
+        }
+
+        let mut transaction = global_data.data_base.begin().await?;
+        let updated = sqlx::query!(
+            r#"UPDATE account_list SET monthly_budget = $1 WHERE id = $2"#,
+            budget,
+            account_id
+        )
+            .execute(&mut *transaction)
+            .await?;
+
+        if updated.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("Account {} not found", account_id));
+        }
+
+        // The account is taken out of or put back to the pool if the budget is reached or not.
+        sqlx::query!(r#"SELECT check_account_budget($1)"#, account_id)
+            .execute(&mut *transaction)
+            .await?;
+        transaction.commit().await?;
+
+        match budget {
+            Some(budget) => info!("The monthly budget of account {} has been set to {}.", account_id, budget),
+            None => info!("The monthly budget of account {} has been removed.", account_id),
+        }
+
+        Ok(())
+    }
+}
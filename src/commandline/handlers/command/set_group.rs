@@ -0,0 +1,50 @@
+use crate::commandline::handlers::describer::{CommandDescription, CommandHandler};
+use crate::data::config::entity::runtime_data::GlobalData;
+use crate::data::database::user_key::find_user;
+use cat_macro::describe;
+use log::info;
+
+#[derive(Default)]
+pub(in crate::commandline::handlers) struct SetGroup;
+
+impl CommandHandler for SetGroup {
+    fn description(&self) -> CommandDescription {
+        describe! {
+            ["set_group" | "sg"] help "Set the group of a user, a user without group can use any model";
+            "user" => "Any api key of the user, or the id of the user",
+            "group" => "The name of the group, or `none` to remove the user from the group",
+        }
+    }
+
+    async fn execute(&self, global_data: &GlobalData, args: &Vec<&str>) -> anyhow::Result<()> {
+        let Some(&key) = args.first() else {
+            return Err(anyhow::anyhow!("Missing user"));
+        };
+
+        let Some(&group) = args.get(1) else {
+            return Err(anyhow::anyhow!("Missing group name"));
+        };
+
+        let group_id = if group == "none" {
+            None
+        } else {
+            let group = sqlx::query!(r#"SELECT id FROM user_group WHERE name = $1"#, group)
+                .fetch_optional(&global_data.data_base)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("Group {} not found", group))?;
+            Some(group.id)
+        };
+
+        let user = find_user(&global_data.data_base, key).await?;
+        sqlx::query!(
+            r#"UPDATE "user" SET group_id = $1 WHERE id = $2"#,
+            group_id,
+            user.id
+        )
+        .execute(&global_data.data_base)
+        .await?;
+
+        info!("User {} has been moved to group {}.", user.id, group);
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+use crate::commandline::handlers::describer::{CommandDescription, CommandHandler};
+use crate::data::config::entity::runtime_data::GlobalData;
+use crate::http::client::util::account_tester::{disable_dead_accounts, test_accounts};
+use cat_macro::describe;
+use log::{info, warn};
+
+#[derive(Default)]
+pub(in crate::commandline::handlers) struct TestAccount;
+
+impl CommandHandler for TestAccount {
+    fn description(&self) -> CommandDescription {
+        describe! {
+            ["test_account" | "ta"] help "Send a minimal request through the accounts in pool, and show the latency, status and models";
+            ("id") => "The id of the account, or `all` to test all the accounts. Default is `all`.",
+            ("disable") => "Disable the accounts rejected by the endpoint (401). Default is false.",
+        }
+    }
+
+    async fn execute(&self, global_data: &GlobalData, args: &Vec<&str>) -> anyhow::Result<()> {
+        let account_id = match args.first() {
+            None | Some(&"all") => None,
+            Some(id) => Some(id.parse::<i32>()?),
+        };
+
+        let disable = match args.get(1) {
+            Some(disable) => disable.parse::<bool>()?,
+            None => false,
+        };
+
+        let results = test_accounts(global_data, account_id).await;
+        if results.is_empty() {
+            return Err(anyhow::anyhow!("No account found in pool"));
+        }
+
+        for result in results.iter() {
+            if result.is_ok() {
+                info!(
+                    "account {} ({}): {:?} in {}ms, models: {}",
+                    result.account_id,
+                    result.endpoint,
+                    result.status,
+                    result.latency_ms,
+                    result.models.join(", ")
+                );
+            } else {
+                warn!(
+                    "account {} ({}): {:?} in {}ms, error: {}",
+                    result.account_id,
+                    result.endpoint,
+                    result.status,
+                    result.latency_ms,
+                    result.error.as_deref().unwrap_or_default()
+                );
+            }
+        }
+
+        if disable {
+            let dead = disable_dead_accounts(global_data, &results).await?;
+            info!("{} dead accounts have been disabled.", dead.len());
+        }
+
+        Ok(())
+    }
+}
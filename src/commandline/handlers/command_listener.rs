@@ -1,7 +1,5 @@
 use std::ops::Deref;
 use std::sync::LazyLock;
-use std::thread::sleep;
-use std::time::Duration;
 
 use colored::Colorize;
 use log::{error, info};
@@ -74,10 +72,13 @@ pub fn add_cmd_listener(global_data: &'static GlobalData) {
                 }
             }
             Err(ReadlineError::Interrupted) => {
-                info!("Server is shutting down, please wait.");
-                sleep(Duration::from_millis(200));
-                info!("shutting down now.");
-                std::process::exit(0);
+                if global_data.request_tracker.is_closed() {
+                    info!("Force shutting down now.");
+                    std::process::exit(1);
+                }
+
+                info!("Server is shutting down, please wait. Press Ctrl-C again to force exit.");
+                global_data.request_tracker.request_shutdown();
             },
             Err(_) => {}
         }
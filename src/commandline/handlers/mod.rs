@@ -1,10 +1,25 @@
+use crate::commandline::handlers::command::add_account::AddAccount;
+use crate::commandline::handlers::command::add_group::AddGroup;
 use crate::commandline::handlers::command::add_user::AddUser;
+use crate::commandline::handlers::command::create_key::CreateKey;
 use crate::commandline::handlers::command::edit_balance::EditUserBalance;
+use crate::commandline::handlers::command::import_account::ImportAccount;
 use crate::commandline::handlers::command::list_account::ListAccount;
+use crate::commandline::handlers::command::list_group::ListGroup;
+use crate::commandline::handlers::command::list_keys::ListKeys;
 use crate::commandline::handlers::command::list_model::ListModel;
 use crate::commandline::handlers::command::manage_account_pool::ManageAccountPool;
+use crate::commandline::handlers::command::reload::Reload;
+use crate::commandline::handlers::command::remove_account::RemoveAccount;
+use crate::commandline::handlers::command::revoke_key::RevokeKey;
+use crate::commandline::handlers::command::rotate_master_key::RotateMasterKey;
 use crate::commandline::handlers::command::search_balance::SearchBalance;
 use crate::commandline::handlers::command::search_user::SearchUser;
+use crate::commandline::handlers::command::set_account::SetAccount;
+use crate::commandline::handlers::command::test_account::TestAccount;
+use crate::commandline::handlers::command::set_account_budget::SetAccountBudget;
+use crate::commandline::handlers::command::margin_report::MarginReport;
+use crate::commandline::handlers::command::set_group::SetGroup;
 use crate::data::config::entity::runtime_data::GlobalData;
 
 #[macro_use]
@@ -21,5 +36,20 @@ command_handler_dispatcher! {
     SearchBalance,
     SearchUser,
     ManageAccountPool,
-    ListModel
+    ListModel,
+    Reload,
+    AddGroup,
+    SetGroup,
+    ListGroup,
+    CreateKey,
+    ListKeys,
+    RevokeKey,
+    RotateMasterKey,
+    AddAccount,
+    RemoveAccount,
+    SetAccount,
+    ImportAccount,
+    TestAccount,
+    SetAccountBudget,
+    MarginReport
 }
\ No newline at end of file
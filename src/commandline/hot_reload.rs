@@ -1,21 +1,29 @@
-use std::fs::File;
-use std::io::BufReader;
 use std::path::Path;
+use std::sync::Arc;
 use std::time::Duration;
 
 use colored::Colorize;
-use log::{error, info};
+use log::{error, info, warn};
 use notify::{PollWatcher, RecursiveMode, Watcher};
+use tokio::runtime::Handle;
 
-use crate::data::config::entity::config_file::Config;
+use crate::data::config::config_helper::{config_dir, get_config, CONFIG_EXTENSIONS};
 use crate::data::config::entity::model_manager::ModelManager;
 use crate::data::config::entity::model_mapping::ModelMapping;
 use crate::data::config::entity::model_price::ModelPriceMap;
+use crate::data::config::entity::model_cost::ModelCostMap;
 use crate::data::config::entity::runtime_data::GlobalData;
+use crate::http::client::util::account_manager::load_account_from_database;
+use crate::http::client::util::counter::concurrency_pool::VecSafePool;
 
+/// The config files watched by the hot reload, without extension.
+const CONFIG_FILES: [&str; 5] = ["config", "model_price", "model_cost", "model", "model_mapping"];
+
+/// Watch the config files and reload them when changed, this should be called in a blocking task.
 pub fn enable_config_hot_reload(global_data: &GlobalData) -> anyhow::Result<()> {
     info!("{}", "Start watching config file.".to_string().green());
 
+    let runtime = Handle::current();
     let config = notify::Config::default()
         .with_compare_contents(true)
         .with_poll_interval(Duration::from_secs(2));
@@ -23,66 +31,23 @@ pub fn enable_config_hot_reload(global_data: &GlobalData) -> anyhow::Result<()>
     let (tx, rx) = std::sync::mpsc::channel();
 
     let mut watcher = PollWatcher::new(tx, config)?;
-    watcher.watch(Path::new("./config/config.json"), RecursiveMode::Recursive)?;
-    watcher.watch(Path::new("./config/model_price.json"), RecursiveMode::Recursive)?;
-    watcher.watch(Path::new("./config/model.json"), RecursiveMode::Recursive)?;
-    watcher.watch(Path::new("./config/model_mapping.json"), RecursiveMode::Recursive)?;
+    watcher.watch(config_dir(), RecursiveMode::NonRecursive)?;
 
     for res in rx {
         match res {
             Ok(event) => {
-                if let Some(path) = event.paths.first() {
-                    if let Some(path) = path.file_name()
-                        && let Some(path) = path.to_str()
-                    {
-                        match path {
-                            "config.json" => {
-                                info!(
-                                    "{}",
-                                    format!("Start hot reload config file: {:?}", event).blue()
-                                );
-                                let config = {
-                                    let file = File::open("./config/config.json")
-                                        .expect("Unable to open file.");
-                                    let config = BufReader::new(file);
-                                    let config: Config = serde_json::from_reader(config)
-                                        .expect("Unable to read json.");
-                                    config
-                                };
-                                *global_data.config.write() = config;
-                                info!("{}", "Hot reload config file success.".green());
-                            }
-                            "model_price.json" => {
-                                info!(
-                                    "{}",
-                                    format!("Start hot reload model price file: {:?}", event)
-                                        .blue()
-                                );
-                                let config = global_data.config.read();
-                                *global_data.model_price.write() = ModelPriceMap::new(&config)?;
-                                info!("{}", "Hot reload price file success.".green());
-                            }
-                            "model.json" => {
-                                info!(
-                                    "{}",
-                                    format!("Start hot reload model file: {:?}", event).blue()
-                                );
-                                let config = global_data.config.read();
-                                *global_data.model_info.write() = ModelManager::new(&config)?;
-                                info!("{}", "Hot reload model file success.".green());
-                            }
-                            "model_mapping.json" => {
-                                info!(
-                                    "{}",
-                                    format!("Start hot reload model mapping file: {:?}", event)
-                                        .blue()
-                                );
-                                let config = global_data.config.read();
-                                *global_data.model_mapping.write() = ModelMapping::new(&config)?;
-                                info!("{}", "Hot reload model mapping file success.".green());
-                            }
-                            _ => {}
-                        }
+                if let Some(path) = event.paths.first()
+                    && is_config_file(path)
+                    && let Some(path) = path.file_name()
+                    && let Some(path) = path.to_str()
+                {
+                    info!(
+                        "{}",
+                        format!("Start hot reload {}: {:?}", path, event).blue()
+                    );
+
+                    if runtime.block_on(reload_config(global_data)).is_ok() {
+                        info!("{}", format!("Hot reload {} success.", path).green());
                     }
                 }
             }
@@ -92,3 +57,68 @@ pub fn enable_config_hot_reload(global_data: &GlobalData) -> anyhow::Result<()>
 
     Ok(())
 }
+
+fn is_config_file(path: &Path) -> bool {
+    let stem = path.file_stem().and_then(|x| x.to_str());
+    let extension = path.extension().and_then(|x| x.to_str());
+
+    stem.is_some_and(|x| CONFIG_FILES.contains(&x))
+        && extension.is_some_and(|x| CONFIG_EXTENSIONS.contains(&x))
+}
+
+/// Reload all the config files, every file is loaded and validated before any of them
+/// is swapped in, so the old config is kept if any of them is invalid.
+/// The account pool will be rebuilt if the settings used by the accounts are changed.
+pub async fn reload_config(global_data: &GlobalData) -> anyhow::Result<()> {
+    let result = try_reload_config(global_data).await;
+
+    match &result {
+        Ok(_) => *global_data.config_error.write() = None,
+        Err(e) => {
+            error!("Reload config failed, the old config is kept: {}", e);
+            *global_data.config_error.write() = Some(e.to_string());
+        }
+    }
+
+    result
+}
+
+async fn try_reload_config(global_data: &GlobalData) -> anyhow::Result<()> {
+    let config = get_config()?;
+    let model_price = ModelPriceMap::new(&config)?;
+    let model_cost = ModelCostMap::new(&config)?;
+    let model_mapping = ModelMapping::new(&config)?;
+    let model_info = ModelManager::new(&config)?;
+
+    let (need_rebuild_pool, need_restart) = {
+        let old = global_data.config.read();
+        (old.need_rebuild_pool(&config), old.need_restart(&config))
+    };
+
+    let account_pool = if need_rebuild_pool {
+        // An owned snapshot, so nothing of the global data is held while the database is accessed.
+        let master_key = global_data.master_key.load_full();
+        let account = load_account_from_database(&config, &global_data.data_base, &master_key).await?;
+        Some(account.to_vec_safe_pool(&config))
+    } else {
+        None
+    };
+
+    if need_restart {
+        warn!("The http server or log settings are changed, which will take effect after a restart.");
+    }
+
+    *global_data.config.write() = config;
+    *global_data.model_price.write() = model_price;
+    *global_data.model_cost.write() = model_cost;
+    *global_data.model_mapping.write() = model_mapping;
+    *global_data.model_info.write() = model_info;
+
+    if let Some(account_pool) = account_pool {
+        let count = account_pool.len();
+        global_data.account_pool.store(Arc::new(account_pool));
+        info!("Account pool has been rebuilt, now {} accounts in pool.", count);
+    }
+
+    Ok(())
+}
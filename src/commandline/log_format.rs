@@ -0,0 +1,68 @@
+use std::time::UNIX_EPOCH;
+
+use fast_log::appender::{Command, FastLogRecord, RecordFormat};
+use serde_json::json;
+
+/// The json format of the log, every record will be written as one json object per line.
+/// The request id of the log line, which is written as `[request_id] message` in this app,
+/// will be extracted to the `request_id` field, and the colors in the message will be removed.
+pub struct JsonLogFormat;
+
+impl RecordFormat for JsonLogFormat {
+    fn do_format(&self, arg: &mut FastLogRecord) {
+        if let Command::CommandRecord = arg.command {
+            let message = strip_ansi_color(&arg.args);
+            let (request_id, message) = split_request_id(&message);
+            let timestamp = arg
+                .now
+                .duration_since(UNIX_EPOCH)
+                .map(|x| x.as_millis())
+                .unwrap_or_default();
+
+            let record = json!({
+                "timestamp": timestamp as u64,
+                "level": arg.level.as_str(),
+                "target": arg.target,
+                "file": arg.file,
+                "line": arg.line,
+                "request_id": request_id,
+                "message": message,
+            });
+
+            arg.formated = format!("{}\n", record);
+        }
+    }
+}
+
+/// Split the `[request_id]` prefix from the message.
+fn split_request_id(message: &str) -> (Option<&str>, &str) {
+    if let Some(rest) = message.strip_prefix('[')
+        && let Some((request_id, message)) = rest.split_once("] ")
+        && !request_id.is_empty()
+        && request_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        (Some(request_id), message)
+    } else {
+        (None, message)
+    }
+}
+
+/// Remove the ansi color escape sequences, which is added by `colored` in text logs.
+fn strip_ansi_color(message: &str) -> String {
+    let mut back = String::with_capacity(message.len());
+    let mut chars = message.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+            continue;
+        }
+        back.push(c);
+    }
+
+    back
+}
@@ -1,3 +1,4 @@
 #[macro_use]
 pub mod handlers;
-pub mod hot_reload;
\ No newline at end of file
+pub mod hot_reload;
+pub mod log_format;
\ No newline at end of file
@@ -1,11 +1,97 @@
+//! Load the config files from the config directory.
+//! The config directory is `./config` by default, which can be changed by the
+//! `--config-dir <path>` flag or the `GPT_CAT_CONFIG_DIR` environment variable.
+//! Every config file can be written in json, toml or yaml, e.g. `config.toml`.
+//!
+//! Any field of `config.json` can be overridden by the environment variables:
+//! - `GPT_CAT_<FIELD>`: The value of the field, e.g. `GPT_CAT_REQUEST_TIMEOUT=30`, nested
+//!   fields are separated by `__`, e.g. `GPT_CAT_PROXY__MY_PROXY__PASSWORD=xxx`.
+//! - `GPT_CAT_<FIELD>_FILE`: Read the value of the field from a file, which is useful for
+//!   docker or kubernetes secrets, e.g. `GPT_CAT_DATABASE_URL_FILE=/run/secrets/database_url`.
+//!
+//! A variable that does not name a field of the config is an error, e.g. a misspelled name.
+//!
+//! The legacy variables `DATABASE_URL`, `HTTP_PORT`, etc. are still supported.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use anyhow::{anyhow, bail};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
 use crate::data::config::entity::config_file::Config;
-use std::fs::File;
-use std::io::BufReader;
+
+/// The prefix of the environment variables that override the config.
+const ENV_PREFIX: &str = "GPT_CAT_";
+
+/// The supported extensions of the config files, in the order of lookup.
+pub const CONFIG_EXTENSIONS: [&str; 4] = ["json", "toml", "yaml", "yml"];
+
+static CONFIG_DIR: LazyLock<PathBuf> = LazyLock::new(|| {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--config-dir"
+            && let Some(dir) = args.next()
+        {
+            return PathBuf::from(dir);
+        }
+
+        if let Some(dir) = arg.strip_prefix("--config-dir=") {
+            return PathBuf::from(dir);
+        }
+    }
+
+    std::env::var("GPT_CAT_CONFIG_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("./config"))
+});
+
+/// Get the directory of the config files.
+pub fn config_dir() -> &'static Path {
+    CONFIG_DIR.as_path()
+}
+
+/// Find the config file by its name without extension, e.g. `config` for `config.json`.
+pub fn find_config_file(name: &str) -> anyhow::Result<PathBuf> {
+    CONFIG_EXTENSIONS
+        .iter()
+        .map(|extension| config_dir().join(format!("{}.{}", name, extension)))
+        .find(|path| path.exists())
+        .ok_or_else(|| {
+            anyhow!(
+                "Unable to find config file {} in {}",
+                name,
+                config_dir().display()
+            )
+        })
+}
+
+/// Read the config file by its name without extension, the format is decided by the extension.
+pub fn read_config_file<T: DeserializeOwned>(name: &str) -> anyhow::Result<T> {
+    let value = read_config_value(name)?;
+    serde_json::from_value(value).map_err(|e| anyhow!("Unable to read config file {}: {}", name, e))
+}
+
+fn read_config_value(name: &str) -> anyhow::Result<Value> {
+    let path = find_config_file(name)?;
+    let content = fs::read_to_string(&path)
+        .map_err(|e| anyhow!("Unable to open config file {}: {}", path.display(), e))?;
+
+    let value = match path.extension().and_then(|x| x.to_str()) {
+        Some("toml") => toml::from_str(&content).map_err(|e| anyhow!("{}", e)),
+        Some("yaml" | "yml") => serde_yaml::from_str(&content).map_err(|e| anyhow!("{}", e)),
+        _ => serde_json::from_str(&content).map_err(|e| anyhow!("{}", e)),
+    };
+
+    value.map_err(|e| anyhow!("Unable to read config file {}: {}", path.display(), e))
+}
 
 pub fn get_config() -> anyhow::Result<Config> {
-    let file = File::open("./config/config.json").expect("Unable to open config file.");
-    let config = BufReader::new(file);
-    let mut config: Config = serde_json::from_reader(config).expect("Unable to read json");
+    let mut value = read_config_value("config")?;
+    let overrides = apply_overrides(&mut value, env_overrides())?;
+    let mut config = to_config(value, &overrides)?;
 
     for (key, value) in std::env::vars() {
         match key.as_str() {
@@ -40,5 +126,185 @@ pub fn get_config() -> anyhow::Result<Config> {
         }
     }
 
+    config.validate()?;
+
+    Ok(config)
+}
+
+/// The `GPT_CAT_*` environment variables that override the config. The secrets read from
+/// files are put first, so that an explicit value can still override them.
+fn env_overrides() -> Vec<(String, String)> {
+    let mut variables = std::env::vars()
+        .filter(|(key, _)| key.starts_with(ENV_PREFIX))
+        .filter(|(key, _)| key != "GPT_CAT_CONFIG_DIR" && !key.starts_with("GPT_CAT_MASTER_KEY"))
+        .collect::<Vec<_>>();
+    variables.sort_by_key(|(key, _)| !key.ends_with("_FILE"));
+    variables
+}
+
+/// Apply the variables to the config value, returns the name and the config path of each variable.
+fn apply_overrides(
+    config: &mut Value,
+    variables: Vec<(String, String)>,
+) -> anyhow::Result<Vec<(String, Vec<String>)>> {
+    let mut overrides = Vec::with_capacity(variables.len());
+    for (name, value) in variables {
+        let key = &name[ENV_PREFIX.len()..];
+        let (key, value) = if let Some(key) = key.strip_suffix("_FILE") {
+            let secret = fs::read_to_string(&value)
+                .map_err(|e| anyhow!("Unable to read secret file {} of {}: {}", value, key, e))?;
+            (key, secret.trim_end_matches(['\r', '\n']).to_string())
+        } else {
+            (key, value)
+        };
+
+        let path = key.split("__").collect::<Vec<_>>();
+        set_value(config, &path, value).map_err(|e| anyhow!("{}: {}", name, e))?;
+        overrides.push((name.clone(), path.into_iter().map(String::from).collect()));
+    }
+
+    Ok(overrides)
+}
+
+/// Read the config from the value. An override that is not a field of the config is rejected,
+/// otherwise it would be dropped silently, e.g. a misspelled name.
+fn to_config(value: Value, overrides: &[(String, Vec<String>)]) -> anyhow::Result<Config> {
+    let config: Config = serde_json::from_value(value)
+        .map_err(|e| anyhow!("Unable to read config file: {}", e))?;
+
+    let fields = serde_json::to_value(&config)?;
+    if let Some((name, _)) = overrides.iter().find(|(_, path)| !has_path(&fields, path)) {
+        bail!("{} does not override any field of the config", name);
+    }
+
     Ok(config)
-}
\ No newline at end of file
+}
+
+/// Check if the path exists in the value, the keys are matched case-insensitively.
+fn has_path(value: &Value, path: &[String]) -> bool {
+    path.iter()
+        .try_fold(value, |current, key| {
+            current
+                .as_object()?
+                .iter()
+                .find(|(x, _)| x.eq_ignore_ascii_case(key))
+                .map(|(_, x)| x)
+        })
+        .is_some()
+}
+
+/// Set the value in the path, the keys are matched case-insensitively.
+/// The value is kept as a string if the current value is a string, otherwise it is
+/// parsed as json, e.g. numbers and booleans.
+fn set_value(config: &mut Value, path: &[&str], value: String) -> anyhow::Result<()> {
+    let Some((last, parents)) = path.split_last() else {
+        bail!("Empty config path");
+    };
+
+    let mut current = config;
+    for key in parents {
+        let object = as_object(current)?;
+        let key = find_key(object, key);
+        current = object.entry(key).or_insert_with(|| Value::Object(Map::new()));
+    }
+
+    let object = as_object(current)?;
+    let key = find_key(object, last);
+    let value = match object.get(&key) {
+        Some(Value::String(_)) => Value::String(value),
+        _ => serde_json::from_str(&value).unwrap_or(Value::String(value)),
+    };
+    object.insert(key, value);
+
+    Ok(())
+}
+
+fn as_object(value: &mut Value) -> anyhow::Result<&mut Map<String, Value>> {
+    value
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("Config path is not an object"))
+}
+
+/// Find the existing key which is equal to the given key case-insensitively,
+/// or use the key in lowercase.
+fn find_key(object: &Map<String, Value>, key: &str) -> String {
+    object
+        .keys()
+        .find(|x| x.eq_ignore_ascii_case(key))
+        .cloned()
+        .unwrap_or_else(|| key.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::data::config::entity::config_file::LogFormat;
+
+    #[test]
+    fn test_set_value() {
+        let mut config = json!({
+            "database_url": "postgres://localhost",
+            "request_timeout": 60,
+            "proxy": {"My_Proxy": {"password": "old"}}
+        });
+
+        set_value(&mut config, &["REQUEST_TIMEOUT"], "30".to_string()).unwrap();
+        assert_eq!(config["request_timeout"], json!(30));
+
+        // A string field keeps the value as a string even if it looks like json.
+        set_value(&mut config, &["DATABASE_URL"], "123".to_string()).unwrap();
+        assert_eq!(config["database_url"], json!("123"));
+
+        set_value(&mut config, &["PROXY", "MY_PROXY", "PASSWORD"], "new".to_string()).unwrap();
+        assert_eq!(config["proxy"]["My_Proxy"]["password"], json!("new"));
+
+        // The missing fields are created in lowercase.
+        set_value(&mut config, &["LOG_FORMAT"], "json".to_string()).unwrap();
+        assert_eq!(config["log_format"], json!("json"));
+    }
+
+    fn variables(variables: &[(&str, &str)]) -> Vec<(String, String)> {
+        variables.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
+    }
+
+    fn base_config() -> Value {
+        json!({"endpoint": {"OpenAI": "https://api.openai.com/v1/chat/completions"}})
+    }
+
+    #[test]
+    fn test_override_config() {
+        let mut value = base_config();
+        let overrides = apply_overrides(&mut value, variables(&[
+            // The http settings are flattened into the top level of the config.
+            ("GPT_CAT_HTTP_PORT", "8080"),
+            ("GPT_CAT_REQUEST_TIMEOUT", "30"),
+            ("GPT_CAT_LOG_FORMAT", "json"),
+            ("GPT_CAT_PROXY__MY_PROXY", r#"{"scheme": "http", "address": "127.0.0.1:8080", "password": ""}"#),
+            ("GPT_CAT_PROXY__MY_PROXY__PASSWORD", "secret"),
+        ])).unwrap();
+
+        let config = to_config(value, &overrides).unwrap();
+        assert_eq!(config.http_config.http_port, 8080);
+        assert_eq!(config.request_timeout, 30);
+        assert_eq!(config.log_format, LogFormat::Json);
+        assert_eq!(config.proxy.unwrap()["my_proxy"].password, "secret");
+    }
+
+    #[test]
+    fn test_reject_unknown_override() {
+        for name in ["GPT_CAT_HTTP_CONFIG__HTTP_PORT", "GPT_CAT_REQUEST_TIMOUT"] {
+            let mut value = base_config();
+            let overrides = apply_overrides(&mut value, variables(&[(name, "8080")])).unwrap();
+            assert!(to_config(value, &overrides).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_set_invalid_value() {
+        let mut config = json!({"request_timeout": 60});
+        assert!(set_value(&mut config, &[], "1".to_string()).is_err());
+        assert!(set_value(&mut config, &["REQUEST_TIMEOUT", "VALUE"], "1".to_string()).is_err());
+    }
+}
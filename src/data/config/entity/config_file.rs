@@ -1,4 +1,6 @@
+use anyhow::{anyhow, bail};
 use hashbrown::HashMap;
+use reqwest::Url;
 use serde::{Deserialize, Serialize};
 use std::ops::Deref;
 
@@ -7,20 +9,43 @@ use crate::data::config::entity::endpoint::Endpoint;
 const fn default_number_can_retries() -> u32 { 3 }
 const fn default_request_timeout() -> u64 { 15 }
 const fn default_request_concurrency_count() -> u32 { 10 }
+const fn default_shutdown_timeout() -> u64 { 60 }
+const fn default_auth_cache_ttl() -> u64 { 30 }
+const fn default_queue_capacity() -> usize { 200 }
+const fn default_queue_max_wait() -> u64 { 30 }
+const fn default_stream_usage() -> bool { true }
 const fn default_http_address() -> u16 { 7117 }
 const fn default_https_address() -> u16 { 11711 }
+fn default_retry_status() -> Vec<u16> { vec![401, 403, 408, 429] }
 fn default_address() -> String { "0.0.0.0".to_string() }
 fn default_pem_path() -> String { "./ssl/fullchain.pem".to_string() }
 fn default_key_path() -> String { "./ssl/key.pem".to_string() }
 
 /// The config file of the server.
-/// This will be read from ./config/config.json
+/// This will be read from ./config/config.json, or the config file in the `--config-dir`.
 /// # Fields
 /// - endpoint: A map of each endpoint, save the url for the endpoint.
 /// - database_url: The database url of the server.
 /// - number_can_retries: The number of retries when the request fails.
-/// - request_concurrency_count: The number of concurrent requests.
+/// - retry_status: The 4xx status codes of the endpoint that are retried on another account, the
+///   5xx codes are always retried, and the other codes are returned to the client at once.
+/// - request_concurrency_count: The default max concurrent requests of an account, which can be
+///   overridden by `max_concurrency` in `account_list`.
+/// - reserved_concurrency: The concurrent requests of each account that are reserved for the
+///   groups with a priority greater than 0.
+/// - model_concurrency: The max concurrent requests of each model on a single account.
 /// - proxy: The proxy server use if an account specified.
+/// - log_format: The format of the log, either colored text or json.
+/// - shutdown_timeout: The seconds to wait for the running requests when shutting down.
+/// - auth_cache_ttl: The seconds to cache the owner of a key, `0` to disable the cache.
+/// - account_test_interval: The seconds between the scheduled account tests, `0` to disable them.
+/// - admin_key: The key of the admin api, the admin api is disabled if it is not set.
+/// - queue_capacity: The max requests waiting for a free account, the others are rejected at once.
+/// - queue_max_wait: The max seconds a request waits for a free account.
+/// - affinity_ttl: The seconds the later turns of a conversation prefer the account of the
+///   previous turn, so that the prompt cache of the endpoint can be hit, `0` to disable it.
+/// - stream_usage: Ask the OpenAI endpoints to report the usage of the stream responses, which
+///   is needed to bill the cached and reasoning tokens, disable it if an endpoint rejects `stream_options`.
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Config {
     pub endpoint: EndpointMap,
@@ -30,8 +55,13 @@ pub struct Config {
 
     #[serde(default = "default_number_can_retries")]
     pub number_can_retries: u32,
+    #[serde(default = "default_retry_status")]
+    pub retry_status: Vec<u16>,
     #[serde(default = "default_request_concurrency_count")]
     pub request_concurrency_count: u32,
+    #[serde(default)]
+    pub reserved_concurrency: u32,
+    pub model_concurrency: Option<HashMap<String, u32>>,
     #[serde(default = "default_request_timeout")]
     pub request_timeout: u64,
 
@@ -42,6 +72,108 @@ pub struct Config {
     pub endpoint_mapping: Option<HashMap<String, (Endpoint, Option<String>)>>,
 
     pub proxy: Option<HashMap<String, ProxyConfig>>,
+
+    #[serde(default)]
+    pub log_format: LogFormat,
+
+    #[serde(default = "default_shutdown_timeout")]
+    pub shutdown_timeout: u64,
+
+    #[serde(default = "default_auth_cache_ttl")]
+    pub auth_cache_ttl: u64,
+
+    #[serde(default)]
+    pub account_test_interval: u64,
+
+    pub admin_key: Option<String>,
+
+    #[serde(default = "default_queue_capacity")]
+    pub queue_capacity: usize,
+    #[serde(default = "default_queue_max_wait")]
+    pub queue_max_wait: u64,
+
+    #[serde(default)]
+    pub affinity_ttl: u64,
+
+    #[serde(default = "default_stream_usage")]
+    pub stream_usage: bool,
+}
+
+impl Config {
+    /// Validate the config, so that an invalid config will never be swapped in.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.number_can_retries == 0 {
+            bail!("number_can_retries must be greater than 0");
+        }
+        if let Some(status) = self.retry_status.iter().find(|x| !(400..500).contains(*x)) {
+            bail!("retry_status must be 4xx codes, found {}", status);
+        }
+        if self.request_concurrency_count == 0 {
+            bail!("request_concurrency_count must be greater than 0");
+        }
+        if let Some(model_concurrency) = &self.model_concurrency
+            && let Some((model, _)) = model_concurrency.iter().find(|(_, count)| **count == 0)
+        {
+            bail!("model_concurrency of {} must be greater than 0", model);
+        }
+        if self.request_timeout == 0 {
+            bail!("request_timeout must be greater than 0");
+        }
+
+        for (endpoint, url) in self.endpoint.iter() {
+            Url::parse(url).map_err(|e| anyhow!("Invalid url of endpoint {}: {}", endpoint, e))?;
+        }
+
+        if let Some(mapping) = &self.endpoint_mapping {
+            for (name, (_, url)) in mapping.iter() {
+                if let Some(url) = url {
+                    Url::parse(url).map_err(|e| anyhow!("Invalid url of endpoint {}: {}", name, e))?;
+                }
+            }
+        }
+
+        if let Some(proxy) = &self.proxy {
+            for (name, proxy) in proxy.iter() {
+                if !["http", "https", "socks5", "socks5h"].contains(&proxy.scheme.as_str()) {
+                    bail!("Unsupported scheme of proxy {}: {}", name, proxy.scheme);
+                }
+                if proxy.address.is_empty() {
+                    bail!("Missing address of proxy {}", name);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check if the account pool should be rebuilt when this config is replaced by the new one,
+    /// the clients of the accounts are built with these settings.
+    pub fn need_rebuild_pool(&self, new: &Config) -> bool {
+        self.endpoint != new.endpoint
+            || self.endpoint_mapping != new.endpoint_mapping
+            || self.proxy != new.proxy
+            || self.request_timeout != new.request_timeout
+            || self.request_concurrency_count != new.request_concurrency_count
+            || self.reserved_concurrency != new.reserved_concurrency
+            || self.stream_usage != new.stream_usage
+            || self.model_concurrency != new.model_concurrency
+    }
+
+    /// Check if the http server settings are changed, which can only take effect after a restart.
+    pub fn need_restart(&self, new: &Config) -> bool {
+        self.http_config != new.http_config || self.log_format != new.log_format
+    }
+}
+
+/// The format of the log output.
+/// - Text: The colored text, which is easy to read in the terminal.
+/// - Json: One json object per line, which is easy to collect by log systems.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
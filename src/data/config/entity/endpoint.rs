@@ -53,12 +53,9 @@ impl Endpoint {
     }
 
     /// Get the endpoint from string. All the strings are all caps.
-    /// This function will return the endpoint from string if the string is not found in config,
-    /// it will panic
-    /// **IMPORTANT NOTE**
-    /// Because of string cannot be enumeration, this function will not return a result, but will panic if
-    /// you didn't pass a valid name for every endpoint.
-    pub fn from_str(s: &'static str, config: &Config) -> anyhow::Result<Endpoint> {
+    /// This function will return the endpoint from string, or the alias defined in
+    /// `endpoint_mapping` of the config, an error will be returned if it is not found.
+    pub fn from_str(s: &str, config: &Config) -> anyhow::Result<Endpoint> {
         let endpoint = match s {
             "OpenAI" => Some(Endpoint::OpenAI),
             "QianWen" => Some(Endpoint::QianWen),
@@ -72,7 +69,7 @@ impl Endpoint {
                     .as_ref()
                     .and_then(|x| { x.get(s) })
                     .cloned()
-                    .map(|x| Endpoint::Alias(Cow::from(s), Box::new(x.0)))
+                    .map(|x| Endpoint::Alias(Cow::Owned(s.to_string()), Box::new(x.0)))
             })
             .ok_or(anyhow::anyhow!("Endpoint {} not found in config", s))
     }
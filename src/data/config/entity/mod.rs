@@ -2,5 +2,7 @@ pub mod config_file;
 pub mod endpoint;
 pub mod model_manager;
 pub mod model_price;
+pub mod model_cost;
 pub mod runtime_data;
-pub mod model_mapping;
\ No newline at end of file
+pub mod model_mapping;
+pub mod model_access;
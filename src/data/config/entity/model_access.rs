@@ -0,0 +1,85 @@
+/// The models that a user is allowed to use, which is decided by the group of the user.
+/// The user can use any model if the user is not in any group.
+/// It is loaded and cached with the owner of the key, see `user_key::find_key_owner`.
+/// # Fields
+/// - patterns: The allowed models of the group, `*` and `?` wildcards are supported.
+#[derive(Debug, Clone, Default)]
+pub struct ModelAllowList {
+    patterns: Option<Vec<String>>,
+}
+
+impl ModelAllowList {
+    /// The allow list of a group, `None` if the user is not in any group.
+    pub fn new(patterns: Option<Vec<String>>) -> Self {
+        ModelAllowList { patterns }
+    }
+
+    /// Check if the model is allowed.
+    pub fn allows(&self, model: &str) -> bool {
+        match &self.patterns {
+            None => true,
+            Some(patterns) => patterns.iter().any(|pattern| glob_match(pattern, model)),
+        }
+    }
+}
+
+/// Match the text with the glob pattern, `*` matches any sequence and `?` matches any
+/// single character.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("gpt-4o", "gpt-4o"));
+        assert!(!glob_match("gpt-4o", "gpt-4o-mini"));
+        assert!(glob_match("gpt-4*", "gpt-4o-mini"));
+        assert!(glob_match("gpt-4*", "gpt-4"));
+        assert!(glob_match("*-mini", "gpt-4o-mini"));
+        assert!(glob_match("gpt-?o", "gpt-4o"));
+        assert!(!glob_match("gpt-?o", "gpt-o"));
+        assert!(glob_match("*4*mini*", "gpt-4o-mini-2024"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("", "gpt-4o"));
+        // The pattern has to backtrack after the first `-` does not match.
+        assert!(glob_match("*-turbo", "gpt-3.5-turbo"));
+    }
+
+    #[test]
+    fn test_allow_list() {
+        assert!(ModelAllowList::new(None).allows("gpt-4o"));
+
+        let allow_list = ModelAllowList::new(Some(vec!["gpt-4o*".to_string(), "claude-?".to_string()]));
+        assert!(allow_list.allows("gpt-4o-mini"));
+        assert!(allow_list.allows("claude-3"));
+        assert!(!allow_list.allows("o1"));
+
+        assert!(!ModelAllowList::new(Some(vec![])).allows("gpt-4o"));
+    }
+}
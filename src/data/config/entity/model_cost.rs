@@ -0,0 +1,60 @@
+use hashbrown::HashMap;
+use serde::Deserialize;
+use crate::data::config::config_helper::{find_config_file, read_config_file};
+use crate::data::config::entity::config_file::Config;
+use crate::data::config::entity::endpoint::Endpoint;
+use crate::data::config::entity::model_price::ModelPriceValue;
+
+type ModelName = String;
+
+/// The cost file as written, the endpoints are resolved when it is loaded.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ModelCostFile {
+    endpoint: HashMap<String, HashMap<ModelName, ModelPriceValue>>,
+    account: HashMap<i32, HashMap<ModelName, ModelPriceValue>>,
+}
+
+/// A map contains what the endpoints charge us for the models, in the same format of the
+/// model price. The models are the ones sent to the endpoint, i.e. after the model mapping.
+/// # Fields
+/// - endpoint: The cost of the models on each endpoint.
+/// - account: The cost of the models on a single account, which overrides the cost of its endpoint.
+#[derive(Debug, Default)]
+pub struct ModelCostMap {
+    endpoint: HashMap<Endpoint, HashMap<ModelName, ModelPriceValue>>,
+    account: HashMap<i32, HashMap<ModelName, ModelPriceValue>>,
+}
+
+impl ModelCostMap {
+    /// Load the costs from `model_cost`, the costs are unknown if the file does not exist.
+    pub fn new(config: &Config) -> anyhow::Result<Self> {
+        if find_config_file("model_cost").is_err() {
+            return Ok(Self::default());
+        }
+
+        let file: ModelCostFile = read_config_file("model_cost")?;
+        for (model, cost) in file.endpoint.values().chain(file.account.values()).flatten() {
+            cost.validate(model)?;
+        }
+
+        let endpoint = file
+            .endpoint
+            .into_iter()
+            .map(|(key, value)| Ok((Endpoint::from_str(&key, config)?, value)))
+            .collect::<anyhow::Result<HashMap<_, _>>>()?;
+
+        Ok(Self {
+            endpoint,
+            account: file.account,
+        })
+    }
+
+    /// Get the cost of the model on the account, `None` if it is unknown.
+    pub fn get(&self, account_id: i32, endpoint: &Endpoint, model: &str) -> Option<&ModelPriceValue> {
+        self.account
+            .get(&account_id)
+            .and_then(|x| x.get(model))
+            .or_else(|| self.endpoint.get(endpoint).and_then(|x| x.get(model)))
+    }
+}
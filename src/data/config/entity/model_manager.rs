@@ -1,5 +1,5 @@
 use hashbrown::{HashMap, HashSet};
-use std::fs::File;
+use crate::data::config::config_helper::read_config_file;
 use crate::data::config::entity::config_file::Config;
 use crate::data::config::entity::endpoint::Endpoint;
 use crate::data::config::entity::model_mapping::ModelMapping;
@@ -18,13 +18,12 @@ pub struct ModelManager {
 
 impl ModelManager {
     pub(crate) fn new(config: &Config) -> anyhow::Result<Self> {
-        let file = File::open("./config/model.json").expect("Unable to open model file.");
         let mut info = {
-            let info: HashMap<String, HashSet<String>> = serde_json::from_reader(file).expect("Unable to read json");
+            let info: HashMap<String, HashSet<String>> = read_config_file("model")?;
             info
                 .into_iter()
                 .map(|(key, value)| {
-                    let endpoint = Endpoint::from_str(key.leak(), config)?;
+                    let endpoint = Endpoint::from_str(&key, config)?;
                     Ok((endpoint, value))
                 })
                 .collect::<anyhow::Result<HashMap<_, _>>>()?
@@ -64,6 +63,27 @@ impl ModelManager {
             .unwrap_or(false)
     }
 
+    /// Get any model that is available for the endpoint.
+    pub fn any_model(&self, endpoint: &Endpoint) -> Option<&str> {
+        self
+            .info
+            .get(endpoint)
+            .and_then(|x| x.iter().next())
+            .map(|x| x.as_str())
+    }
+
+    /// List every model with one of the endpoints it is available for.
+    pub fn list_models(&self) -> Vec<(&str, &Endpoint)> {
+        let mut back = HashMap::new();
+        for (endpoint, models) in self.info.iter() {
+            for model in models.iter() {
+                back.entry(model.as_str()).or_insert(endpoint);
+            }
+        }
+
+        back.into_iter().collect()
+    }
+
     /// Check if the model is available for any endpoint.
     pub fn has_model(&self, model: &str) -> bool {
         self.global_info.contains(model)
@@ -1,4 +1,4 @@
-use std::fs::File;
+use crate::data::config::config_helper::read_config_file;
 use crate::data::config::entity::endpoint::Endpoint;
 use hashbrown::HashMap;
 use serde::{Deserialize, Serialize};
@@ -24,14 +24,11 @@ impl Deref for ModelMapping {
 
 impl ModelMapping {
     pub(crate) fn new(config: &Config) -> anyhow::Result<Self> {
-        let file = File::open("./config/model_mapping.json")
-            .expect("Unable to open model mapping file.");
-        
-        let mapping: HashMap<String, HashMap<String, String>> = serde_json::from_reader(file).expect("Unable to read json");
+        let mapping: HashMap<String, HashMap<String, String>> = read_config_file("model_mapping")?;
         let mapping = mapping
             .into_iter()
             .map(|(key, value)| {
-                let endpoint = Endpoint::from_str(key.leak(), config)?;
+                let endpoint = Endpoint::from_str(&key, config)?;
                 Ok((endpoint, value))
             })
             .collect::<anyhow::Result<HashMap<_, _>>>()?;
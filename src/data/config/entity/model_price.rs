@@ -1,7 +1,8 @@
 use hashbrown::HashMap;
+use anyhow::bail;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use std::fs::File;
+use crate::data::config::config_helper::read_config_file;
 use std::ops::Deref;
 use crate::data::config::entity::config_file::Config;
 use crate::data::config::entity::model_mapping::ModelMapping;
@@ -22,16 +23,23 @@ pub struct ModelPriceMap {
 pub enum ModelPriceValue {
     PerToken(ModelPerToken),
     PerTimes(ModelPerTimes),
+    Tiered(ModelTiered),
 }
 
 /// The value of the model price.
 /// # Fields
 /// - input_price: The price of the input.
 /// - output_price: The price of the output.
+/// - cached_input_price: The price of the input that hits the prompt cache, same as the input if not set.
+/// - reasoning_price: The price of the reasoning, same as the output if not set.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ModelPerToken {
     pub input_price: Decimal,
     pub output_price: Decimal,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cached_input_price: Option<Decimal>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_price: Option<Decimal>,
 }
 
 /// The value of the model price.
@@ -42,6 +50,109 @@ pub struct ModelPerTimes {
     pub price: Decimal,
 }
 
+/// The value of the model price, which is decided by the length of the prompt.
+/// # Fields
+/// - tiers: The tiers in the ascending order of `max_input_tokens`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelTiered {
+    pub tiers: Vec<ModelPriceTier>,
+}
+
+/// A tier of the model price.
+/// # Fields
+/// - max_input_tokens: The max input tokens of the prompts in this tier, no limit if not set.
+/// - price: The price of the prompts in this tier.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelPriceTier {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_input_tokens: Option<u64>,
+    #[serde(flatten)]
+    pub price: ModelPerToken,
+}
+
+/// The tokens used by a request, the cached input and the reasoning are billed separately.
+/// # Fields
+/// - input: The input tokens that miss the prompt cache.
+/// - cached_input: The input tokens that hit the prompt cache.
+/// - output: The output tokens sent to the user.
+/// - reasoning: The reasoning tokens, which are not sent to the user.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct TokenUsage {
+    pub input: u64,
+    pub cached_input: u64,
+    pub output: u64,
+    pub reasoning: u64,
+}
+
+impl TokenUsage {
+    /// The length of the prompt, including the cached part.
+    pub fn prompt(&self) -> u64 {
+        self.input + self.cached_input
+    }
+}
+
+impl ModelPerToken {
+    pub fn get_cached_input_price(&self) -> Decimal {
+        self.cached_input_price.unwrap_or(self.input_price)
+    }
+
+    pub fn get_reasoning_price(&self) -> Decimal {
+        self.reasoning_price.unwrap_or(self.output_price)
+    }
+
+    pub fn calculate(&self, usage: &TokenUsage) -> Decimal {
+        self.input_price * Decimal::from(usage.input)
+            + self.get_cached_input_price() * Decimal::from(usage.cached_input)
+            + self.output_price * Decimal::from(usage.output)
+            + self.get_reasoning_price() * Decimal::from(usage.reasoning)
+    }
+}
+
+impl ModelPriceValue {
+    /// The price per token of a prompt with the length, `None` if the model is priced per times.
+    /// The prompts longer than all the tiers use the last tier.
+    pub fn per_token(&self, prompt_tokens: u64) -> Option<&ModelPerToken> {
+        match self {
+            ModelPriceValue::PerToken(token) => Some(token),
+            ModelPriceValue::PerTimes(_) => None,
+            ModelPriceValue::Tiered(tiered) => tiered
+                .tiers
+                .iter()
+                .find(|x| x.max_input_tokens.is_none_or(|max| prompt_tokens <= max))
+                .or(tiered.tiers.last())
+                .map(|x| &x.price),
+        }
+    }
+
+    /// The total price of a request with the usage.
+    pub fn calculate(&self, usage: &TokenUsage) -> Decimal {
+        match self {
+            ModelPriceValue::PerTimes(times) => times.price,
+            _ => self.per_token(usage.prompt()).map_or(Decimal::ZERO, |x| x.calculate(usage)),
+        }
+    }
+
+    /// Check if the tiers can be used.
+    pub fn validate(&self, model: &str) -> anyhow::Result<()> {
+        if let ModelPriceValue::Tiered(tiered) = self {
+            if tiered.tiers.is_empty() {
+                bail!("The price of {} has no tier", model);
+            }
+
+            let sorted = tiered.tiers.windows(2).all(|x| match (x[0].max_input_tokens, x[1].max_input_tokens) {
+                (Some(a), Some(b)) => a < b,
+                (Some(_), None) => true,
+                (None, _) => false,
+            });
+            if !sorted {
+                bail!("The tiers of {} must be in the ascending order of max_input_tokens", model);
+            }
+        }
+
+        Ok(())
+    }
+}
+
 impl Deref for ModelPriceMap {
     type Target = HashMap<ModelName, ModelPriceValue>;
 
@@ -52,9 +163,10 @@ impl Deref for ModelPriceMap {
 
 impl ModelPriceMap {
     pub fn new(config: &Config) -> anyhow::Result<Self> {
-        let file = File::open("./config/model_price.json")
-            .expect("Unable to open model file.");
-        let mut inner: HashMap<ModelName, ModelPriceValue> = serde_json::from_reader(file).expect("Unable to read json");
+        let mut inner: HashMap<ModelName, ModelPriceValue> = read_config_file("model_price")?;
+        for (model, price) in inner.iter() {
+            price.validate(model)?;
+        }
         let mapping = ModelMapping::new(config)?;
 
         for (_, value) in mapping.iter() {
@@ -68,3 +180,77 @@ impl ModelPriceMap {
         Ok(Self { inner })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn decimal(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    fn tiered() -> ModelPriceValue {
+        serde_json::from_str(
+            r#"{"tiers": [
+                {"max_input_tokens": 1000, "input_price": "0.001", "output_price": "0.002"},
+                {"input_price": "0.002", "output_price": "0.004", "cached_input_price": "0.0005"}
+            ]}"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_per_token_of_tiers() {
+        let price = tiered();
+        assert!(matches!(price, ModelPriceValue::Tiered(_)));
+        assert_eq!(price.per_token(1000).unwrap().input_price, decimal("0.001"));
+        assert_eq!(price.per_token(1001).unwrap().input_price, decimal("0.002"));
+
+        let times: ModelPriceValue = serde_json::from_str(r#"{"price": "0.1"}"#).unwrap();
+        assert!(times.per_token(1).is_none());
+    }
+
+    #[test]
+    fn test_calculate_cached_and_reasoning() {
+        let price: ModelPriceValue = serde_json::from_str(
+            r#"{"input_price": "0.001", "output_price": "0.002", "cached_input_price": "0.0001", "reasoning_price": "0.003"}"#,
+        )
+        .unwrap();
+        let usage = TokenUsage { input: 100, cached_input: 200, output: 10, reasoning: 20 };
+        assert_eq!(price.calculate(&usage), decimal("0.1") + decimal("0.02") + decimal("0.02") + decimal("0.06"));
+
+        // The cached input and the reasoning fall back to the input and the output price.
+        let price: ModelPriceValue = serde_json::from_str(r#"{"input_price": "0.001", "output_price": "0.002"}"#).unwrap();
+        assert_eq!(price.calculate(&usage), decimal("0.3") + decimal("0.06"));
+    }
+
+    #[test]
+    fn test_calculate_tiers_by_prompt() {
+        let price = tiered();
+        // The cached input counts for the length of the prompt.
+        let usage = TokenUsage { input: 600, cached_input: 600, output: 10, reasoning: 0 };
+        assert_eq!(price.calculate(&usage), decimal("1.2") + decimal("0.3") + decimal("0.04"));
+
+        let usage = TokenUsage { input: 600, cached_input: 0, output: 10, reasoning: 0 };
+        assert_eq!(price.calculate(&usage), decimal("0.6") + decimal("0.02"));
+    }
+
+    #[test]
+    fn test_validate_tiers() {
+        assert!(tiered().validate("model").is_ok());
+
+        let empty = ModelPriceValue::Tiered(ModelTiered { tiers: vec![] });
+        assert!(empty.validate("model").is_err());
+
+        let unsorted: ModelPriceValue = serde_json::from_str(
+            r#"{"tiers": [
+                {"input_price": "0.002", "output_price": "0.004"},
+                {"max_input_tokens": 1000, "input_price": "0.001", "output_price": "0.002"}
+            ]}"#,
+        )
+        .unwrap();
+        assert!(unsorted.validate("model").is_err());
+    }
+}
@@ -1,3 +1,6 @@
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
 use parking_lot::RwLock;
 use reqwest::Client;
 use sqlx::Pool;
@@ -8,9 +11,18 @@ use crate::data::config::entity::endpoint::Endpoint;
 use crate::data::config::entity::model_manager::ModelManager;
 use crate::data::config::entity::model_mapping::ModelMapping;
 use crate::data::config::entity::model_price::ModelPriceMap;
-use crate::http::client::util::counter::concurrency_pool::SafePool;
+use crate::data::config::entity::model_cost::ModelCostMap;
+use crate::data::database::account_secret::MasterKey;
+use crate::data::database::auth_cache::AuthCache;
+use crate::data::database::entity::data_base_account::AccountSettings;
+use crate::http::client::util::account_stats::AccountStats;
+use crate::http::client::util::affinity::AffinityMap;
+use crate::http::client::util::counter::concurrency_pool::{Measured, SafePool, Throttled};
+use crate::http::client::util::rate_limit::RateLimitState;
+use crate::http::client::util::request_queue::RequestQueue;
 use crate::http::client::ResponderDispatcher;
 use crate::http::server::after_handler::ClientEndHandlers;
+use crate::http::server::shutdown::RequestTracker;
 use crate::http::server::pre_handler::dispatcher::pre_handler_dispatcher::ClientJoinHandlers;
 
 /// The visitor of the account, which contains the information of the account.
@@ -21,28 +33,70 @@ use crate::http::server::pre_handler::dispatcher::pre_handler_dispatcher::Client
 /// - endpoint_url: The url of the endpoint.
 /// - responder: The responder dispatcher of the account.
 /// - client: The client of the account.
+/// - rate_limit: The rate limit reported by the endpoint in the last response.
+/// - max_concurrency: The max concurrent requests of the account.
+/// - stream_usage: Ask the endpoint to report the usage of the stream responses.
+/// - stats: The time to first token and error rate observed from the recent requests.
+/// - settings: The settings in database that the visitor is built from.
 pub struct AccountVisitor {
     pub account_id: i32,
     pub endpoint: Endpoint,
-    pub endpoint_url: &'static str,
+    pub endpoint_url: String,
     pub responder: ResponderDispatcher,
     pub client: Client,
+    pub rate_limit: RateLimitState,
+    pub max_concurrency: u32,
+    pub stream_usage: bool,
+    pub stats: AccountStats,
+    pub settings: AccountSettings,
+}
+
+/// The accounts in the pool, the pools are shared between the snapshots, so that an
+/// unchanged account keeps its concurrency slots when a new snapshot is published.
+pub type AccountPool = Vec<Arc<SafePool<AccountVisitor>>>;
+
+impl Throttled for AccountVisitor {
+    fn is_throttled(&self) -> bool {
+        self.rate_limit.is_exhausted()
+    }
+}
+
+impl Measured for AccountVisitor {
+    fn speed(&self) -> Option<f64> {
+        self.stats.speed()
+    }
 }
 
 /// The global data, which contains the data that will be used in the whole server.
 /// # Fields
 /// - data_base: The database connection.
-/// - account_pool: The account pool, which is used to store the account information.
+/// - account_pool: The snapshot of the account pool, a request keeps the snapshot it has loaded,
+///   and the changes of the pool are published as a new snapshot.
 /// - config: The config of the server.
 /// - model_price: The model price map, which contains the price of the model.
+/// - model_cost: The model cost map, which contains what the endpoints charge for the model.
 /// - model_info: The model manager, which contains the model info.
+/// - config_error: The error of the last config reload, `None` if the config is loaded cleanly.
+/// - request_tracker: The tracker of the running request tasks, used by the graceful shutdown.
+/// - master_key: The master key that encrypts the api keys of the accounts, it is loaded as a snapshot
+///   so that no lock is held while the database is accessed.
+/// - auth_cache: The cache of the key owners, which is invalidated by the database notifications.
+/// - request_queue: The queue of the requests waiting for a free account.
+/// - affinity: The accounts that served the recent conversations.
 pub struct GlobalData {
     pub data_base: Pool<Postgres>,
-    pub account_pool: RwLock<Vec<SafePool<AccountVisitor>>>,
+    pub account_pool: ArcSwap<AccountPool>,
     pub config: RwLock<Config>,
     pub model_price: RwLock<ModelPriceMap>,
+    pub model_cost: RwLock<ModelCostMap>,
     pub model_mapping: RwLock<ModelMapping>,
     pub model_info: RwLock<ModelManager>,
+    pub config_error: RwLock<Option<String>>,
+    pub request_tracker: RequestTracker,
+    pub master_key: ArcSwap<MasterKey>,
+    pub auth_cache: AuthCache,
+    pub request_queue: RequestQueue,
+    pub affinity: AffinityMap,
 }
 
 /// The server pipeline, which contains the pre-handler and after-handler of the server.
@@ -1,9 +1,11 @@
 //! ## The config file of the server.
-//! GPT-Cat will read config from ./config/_.json
+//! GPT-Cat will read config from ./config/_.json, the directory and the format can be
+//! changed, see [config_helper] for more details.
 //! This App has the following config items:
 //! **config.json** The config file of the server, including endpoint url, database path, etc.
 //! **model.json** Available model list, including which endpoint have which model
 //! **model_price.json** The price of each model
+//! **model_cost.json** (optional) The cost of each model charged by the endpoints or the accounts
 
 pub mod entity;
 pub mod config_helper;
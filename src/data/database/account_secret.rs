@@ -0,0 +1,263 @@
+//! The envelope encryption of the upstream api keys in `account_list`.
+//! Every account has its own random data key, which encrypts the api key and is itself
+//! encrypted by the master key, so rotating the master key only re-encrypts the data keys.
+//! Both are stored as `base64(nonce || ciphertext || tag)` with AES-256-GCM.
+//!
+//! The master key is 32 bytes encoded in base64, which is read from:
+//! - `GPT_CAT_MASTER_KEY`: The master key itself.
+//! - `GPT_CAT_MASTER_KEY_FILE`: The file contains the master key, `<config_dir>/master.key` by default.
+//!
+//! A new master key can be generated by `openssl rand -base64 32`.
+//!
+//! The table of the accounts is locked while the data keys are written or rotated, and the
+//! writers check the master key under the lock, so no data key is wrapped with a stale master key.
+//! The other instances reload the rotated key from its source by the notification, which only
+//! works if they share the key file, an instance with `GPT_CAT_MASTER_KEY` has to be restarted.
+
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail};
+use aws_lc_rs::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use log::info;
+use sqlx::{Pool, Transaction};
+use sqlx_postgres::{PgExecutor, Postgres};
+
+use crate::data::config::config_helper::config_dir;
+
+const KEY_LEN: usize = 32;
+
+/// The channel of the notification sent when the master key is rotated.
+pub const MASTER_KEY_CHANGE_CHANNEL: &str = "master_key_change";
+
+/// Where the master key is read from, the new key will be written back to the file
+/// when it is rotated.
+#[derive(Debug, Clone)]
+pub enum MasterKeySource {
+    Env,
+    File(PathBuf),
+}
+
+/// The master key that encrypts the data key of each account.
+pub struct MasterKey {
+    key: [u8; KEY_LEN],
+    pub source: MasterKeySource,
+}
+
+/// The encrypted api key of an account, which can only be decrypted with the master key.
+/// # Fields
+/// - encrypted_key: The api key encrypted by the data key.
+/// - data_key: The data key encrypted by the master key.
+#[derive(Debug, Clone)]
+pub struct AccountSecret {
+    pub encrypted_key: String,
+    pub data_key: String,
+}
+
+impl MasterKey {
+    /// Load the master key from the environment or the key file.
+    pub fn load() -> anyhow::Result<Self> {
+        if let Ok(key) = std::env::var("GPT_CAT_MASTER_KEY") {
+            return Self::from_base64(&key, MasterKeySource::Env);
+        }
+
+        let path = std::env::var("GPT_CAT_MASTER_KEY_FILE")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| config_dir().join("master.key"));
+
+        Self::from_file(path)
+    }
+
+    fn from_file(path: PathBuf) -> anyhow::Result<Self> {
+        let key = fs::read_to_string(&path).map_err(|e| {
+            anyhow!(
+                "Unable to read master key from {}: {}, please set GPT_CAT_MASTER_KEY or \
+                GPT_CAT_MASTER_KEY_FILE, a new key can be generated by `openssl rand -base64 32`",
+                path.display(),
+                e
+            )
+        })?;
+
+        Self::from_base64(&key, MasterKeySource::File(path))
+    }
+
+    pub fn from_base64(key: &str, source: MasterKeySource) -> anyhow::Result<Self> {
+        let key = STANDARD
+            .decode(key.trim())
+            .map_err(|e| anyhow!("Invalid master key: {}", e))?;
+
+        let key: [u8; KEY_LEN] = key
+            .try_into()
+            .map_err(|_| anyhow!("Invalid master key: must be {} bytes", KEY_LEN))?;
+
+        Ok(MasterKey { key, source })
+    }
+
+    /// Read the key again from its source, e.g. after it is rotated by another instance.
+    pub fn reload(&self) -> anyhow::Result<Self> {
+        match &self.source {
+            MasterKeySource::Env => Self::load(),
+            MasterKeySource::File(path) => Self::from_file(path.clone()),
+        }
+    }
+
+    /// Generate a random master key with the same source.
+    pub fn generate(source: MasterKeySource) -> anyhow::Result<Self> {
+        Ok(MasterKey { key: random_key()?, source })
+    }
+
+    pub fn to_base64(&self) -> String {
+        STANDARD.encode(self.key)
+    }
+
+    /// Encrypt the api key with a new data key.
+    pub fn encrypt(&self, api_key: &str) -> anyhow::Result<AccountSecret> {
+        let data_key = random_key()?;
+
+        Ok(AccountSecret {
+            encrypted_key: seal(&data_key, api_key.as_bytes())?,
+            data_key: seal(&self.key, &data_key)?,
+        })
+    }
+
+    /// Decrypt the api key, this should only be called when building the client.
+    pub fn decrypt(&self, secret: &AccountSecret) -> anyhow::Result<String> {
+        let data_key: [u8; KEY_LEN] = open(&self.key, &secret.data_key)?
+            .try_into()
+            .map_err(|_| anyhow!("Invalid data key"))?;
+        let api_key = open(&data_key, &secret.encrypted_key)?;
+
+        Ok(String::from_utf8(api_key)?)
+    }
+
+    /// Check if the data key is encrypted by this master key.
+    pub fn opens(&self, data_key: &str) -> bool {
+        open(&self.key, data_key).is_ok()
+    }
+
+    /// Re-encrypt the data key with another master key, the api key is not touched.
+    pub fn rewrap(&self, data_key: &str, new: &MasterKey) -> anyhow::Result<String> {
+        let data_key = open(&self.key, data_key)?;
+        seal(&new.key, &data_key)
+    }
+}
+
+fn random_key() -> anyhow::Result<[u8; KEY_LEN]> {
+    let mut key = [0u8; KEY_LEN];
+    aws_lc_rs::rand::fill(&mut key).map_err(|_| anyhow!("Unable to generate a random key"))?;
+    Ok(key)
+}
+
+fn seal(key: &[u8; KEY_LEN], plain: &[u8]) -> anyhow::Result<String> {
+    let key = LessSafeKey::new(
+        UnboundKey::new(&AES_256_GCM, key).map_err(|_| anyhow!("Invalid encryption key"))?,
+    );
+
+    let mut nonce = [0u8; NONCE_LEN];
+    aws_lc_rs::rand::fill(&mut nonce).map_err(|_| anyhow!("Unable to generate a nonce"))?;
+
+    let mut in_out = plain.to_vec();
+    key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut in_out)
+        .map_err(|_| anyhow!("Unable to encrypt"))?;
+
+    let mut back = nonce.to_vec();
+    back.extend_from_slice(&in_out);
+    Ok(STANDARD.encode(back))
+}
+
+fn open(key: &[u8; KEY_LEN], sealed: &str) -> anyhow::Result<Vec<u8>> {
+    let key = LessSafeKey::new(
+        UnboundKey::new(&AES_256_GCM, key).map_err(|_| anyhow!("Invalid encryption key"))?,
+    );
+
+    let sealed = STANDARD.decode(sealed)?;
+    if sealed.len() < NONCE_LEN {
+        bail!("Invalid encrypted data");
+    }
+
+    let (nonce, in_out) = sealed.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| anyhow!("Invalid nonce"))?;
+
+    let mut in_out = in_out.to_vec();
+    let plain = key
+        .open_in_place(nonce, Aad::empty(), &mut in_out)
+        .map_err(|_| anyhow!("Unable to decrypt, the master key may be wrong"))?;
+
+    Ok(plain.to_vec())
+}
+
+/// Check if the master key opens the data keys in database, `true` if there is no data key yet.
+pub async fn is_current_master_key<'e>(
+    executor: impl PgExecutor<'e>,
+    master_key: &MasterKey,
+) -> anyhow::Result<bool> {
+    let data_key = sqlx::query_scalar!(
+        r#"SELECT data_key AS "data_key!" FROM account_list WHERE data_key IS NOT NULL LIMIT 1"#
+    )
+        .fetch_optional(executor)
+        .await?;
+
+    Ok(data_key.is_none_or(|x| master_key.opens(&x)))
+}
+
+/// Lock the table of the accounts until the transaction ends, so the master key can not be rotated
+/// meanwhile, and check that the master key is not rotated by another instance before.
+pub async fn lock_account_keys(
+    transaction: &mut Transaction<'_, Postgres>,
+    master_key: &MasterKey,
+) -> anyhow::Result<()> {
+    sqlx::query!("LOCK TABLE account_list IN SHARE ROW EXCLUSIVE MODE")
+        .execute(&mut **transaction)
+        .await?;
+
+    if !is_current_master_key(&mut **transaction, master_key).await? {
+        bail!("The master key can not open the data keys in database, it may have been rotated by another instance");
+    }
+
+    Ok(())
+}
+
+/// Encrypt the api keys that are still stored in plain text, e.g. the accounts inserted
+/// before the encryption or by hand.
+pub async fn encrypt_plaintext_accounts(
+    data_base: &Pool<Postgres>,
+    master_key: &MasterKey,
+) -> anyhow::Result<()> {
+    // The table is only locked if there is something to encrypt.
+    let has_plaintext = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM account_list WHERE api_key IS NOT NULL) AS "exists!""#
+    )
+        .fetch_one(data_base)
+        .await?;
+
+    if !has_plaintext {
+        return Ok(());
+    }
+
+    let mut transaction = data_base.begin().await?;
+    lock_account_keys(&mut transaction, master_key).await?;
+
+    let accounts = sqlx::query!(
+        r#"SELECT id, api_key AS "api_key!" FROM account_list WHERE api_key IS NOT NULL"#
+    )
+        .fetch_all(&mut *transaction)
+        .await?;
+
+    for account in accounts.iter() {
+        let secret = master_key.encrypt(&account.api_key)?;
+        sqlx::query!(
+            r#"UPDATE account_list SET api_key = NULL, encrypted_key = $1, data_key = $2 WHERE id = $3"#,
+            secret.encrypted_key,
+            secret.data_key,
+            account.id
+        )
+            .execute(&mut *transaction)
+            .await?;
+    }
+    transaction.commit().await?;
+
+    info!("{} plaintext account keys have been encrypted.", accounts.len());
+    Ok(())
+}
@@ -0,0 +1,124 @@
+//! The in-memory cache of the key owners, so that a request does not need to query the
+//! database to resolve the user.
+//! The entries are invalidated by the `auth_change` notifications, which are sent by the
+//! triggers when a user is deactivated, a key is revoked or the group of a user is changed, see
+//! `sql/migrations/005_auth_change_notify.sql`.
+//! The ttl is a fallback when a notification is lost.
+//! Every invalidation starts a new generation, an owner read from the database in an older
+//! generation is not cached, so a key revoked during the read is never cached.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use hashbrown::HashMap;
+use parking_lot::RwLock;
+use sha2::{Digest, Sha256};
+
+use crate::data::database::user_key::KeyOwner;
+
+/// The channel of the notifications sent by the triggers, the payload is the id of the user.
+pub const AUTH_CHANGE_CHANNEL: &str = "auth_change";
+
+struct CachedOwner {
+    owner: KeyOwner,
+    cached_at: Instant,
+}
+
+/// The cache of key → owner, the keys are stored as their SHA-256 hash.
+/// The generation is only changed with the lock of the entries held.
+#[derive(Default)]
+pub struct AuthCache {
+    entries: RwLock<HashMap<String, CachedOwner>>,
+    generation: AtomicU64,
+}
+
+impl AuthCache {
+    /// Get the owner of the key, `None` if it is not cached or older than the ttl.
+    pub fn get(&self, key: &str, ttl: Duration) -> Option<KeyOwner> {
+        let entries = self.entries.read();
+        let cached = entries.get(&hash(key))?;
+
+        if cached.cached_at.elapsed() >= ttl {
+            return None;
+        }
+
+        Some(cached.owner.clone())
+    }
+
+    /// The current generation, which should be taken before the owner is read from the database.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Cache the owner read in the generation, nothing is cached if the cache has been
+    /// invalidated since then, since the owner may be stale.
+    pub fn insert(&self, key: &str, owner: KeyOwner, generation: u64) {
+        let mut entries = self.entries.write();
+        if self.generation.load(Ordering::Acquire) != generation {
+            return;
+        }
+
+        entries.insert(hash(key), CachedOwner { owner, cached_at: Instant::now() });
+    }
+
+    /// Remove all the keys of the user, called when the user or one of the keys is changed.
+    pub fn invalidate_user(&self, user_id: i32) {
+        let mut entries = self.entries.write();
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        entries.retain(|_, x| x.owner.user_id != user_id);
+    }
+
+    /// Remove all the entries, called when the notifications may be lost.
+    pub fn clear(&self) {
+        let mut entries = self.entries.write();
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        entries.clear();
+    }
+
+    /// Remove the entries older than the ttl.
+    pub fn evict_expired(&self, ttl: Duration) {
+        self.entries.write().retain(|_, x| x.cached_at.elapsed() < ttl);
+    }
+}
+
+fn hash(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::config::entity::model_access::ModelAllowList;
+
+    fn owner(user_id: i32) -> KeyOwner {
+        KeyOwner {
+            key_id: user_id,
+            user_id,
+            is_active: true,
+            expired: false,
+            revoked: false,
+            priority: 0,
+            allow_list: ModelAllowList::default(),
+        }
+    }
+
+    #[test]
+    fn test_skip_owner_read_before_invalidation() {
+        let cache = AuthCache::default();
+        let ttl = Duration::from_secs(60);
+
+        // The key is revoked while its owner is read from the database.
+        let generation = cache.generation();
+        cache.invalidate_user(1);
+        cache.insert("key", owner(1), generation);
+        assert!(cache.get("key", ttl).is_none());
+
+        cache.insert("key", owner(1), cache.generation());
+        assert_eq!(cache.get("key", ttl).map(|x| x.user_id), Some(1));
+
+        cache.invalidate_user(2);
+        assert!(cache.get("key", ttl).is_some());
+        cache.invalidate_user(1);
+        assert!(cache.get("key", ttl).is_none());
+    }
+}
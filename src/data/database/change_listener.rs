@@ -0,0 +1,123 @@
+//! Listen to the notifications sent by the database triggers, so that the in-memory
+//! state is updated soon after the database is changed, even by another instance.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::bail;
+use log::{error, info, warn};
+use sqlx_postgres::PgListener;
+
+use crate::data::config::entity::runtime_data::GlobalData;
+use crate::data::database::account_secret::{is_current_master_key, MASTER_KEY_CHANGE_CHANNEL};
+use crate::data::database::auth_cache::AUTH_CHANGE_CHANNEL;
+use crate::http::client::util::account_manager::{refresh_account, sync_account_pool};
+
+/// The channel of the notifications sent when an account is changed, the payload is the id of the account.
+pub const ACCOUNT_CHANGE_CHANNEL: &str = "account_change";
+
+/// The times to read the rotated master key, the rotating instance replaces the key file
+/// right after the commit, so it may not be replaced yet when the notification arrives.
+const MASTER_KEY_RELOAD_ATTEMPTS: u32 = 5;
+
+/// Listen to the database notifications until the server is stopped.
+/// The notifications sent while disconnected are lost, so the caches are cleared and the
+/// account pool is synced after reconnecting.
+pub async fn listen_database_changes(global_data: &'static GlobalData) {
+    let mut reconnecting = false;
+    loop {
+        if let Err(e) = listen(global_data, reconnecting).await {
+            error!("Database change listener stopped: {}, retrying in 5 seconds.", e);
+        }
+
+        reconnecting = true;
+        tokio::time::sleep(Duration::from_secs(5)).await;
+    }
+}
+
+/// Drop the state that may be stale since some notifications are lost.
+async fn resync(global_data: &GlobalData) {
+    global_data.auth_cache.clear();
+    reload_master_key(global_data).await;
+
+    match sync_account_pool(global_data).await {
+        Ok(count) => info!("Account pool has been synced, now {} accounts in pool.", count),
+        Err(e) => error!("Error when sync the account pool: {}", e),
+    }
+}
+
+/// Reload the master key if it is rotated by another instance, the key is read again from its source,
+/// which only works if the instances share the key file.
+async fn reload_master_key(global_data: &GlobalData) {
+    for _ in 0..MASTER_KEY_RELOAD_ATTEMPTS {
+        let current = global_data.master_key.load_full();
+        match is_current_master_key(&global_data.data_base, &current).await {
+            Ok(true) => return,
+            Ok(false) => {}
+            Err(e) => {
+                error!("Error when check the master key: {}", e);
+                return;
+            }
+        }
+
+        if let Ok(key) = current.reload()
+            && is_current_master_key(&global_data.data_base, &key).await.unwrap_or(false)
+        {
+            global_data.master_key.store(Arc::new(key));
+            info!("The master key rotated by another instance has been reloaded.");
+            return;
+        }
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+
+    error!("The master key has been rotated by another instance, please restart this instance with the new key.");
+}
+
+async fn listen(global_data: &'static GlobalData, reconnecting: bool) -> anyhow::Result<()> {
+    let mut listener = PgListener::connect_with(&global_data.data_base).await?;
+    listener.listen_all([AUTH_CHANGE_CHANNEL, ACCOUNT_CHANGE_CHANNEL, MASTER_KEY_CHANGE_CHANNEL]).await?;
+    info!("Start listening to database changes.");
+
+    // Resync after listening, so that no change is missed between them.
+    if reconnecting {
+        resync(global_data).await;
+    }
+
+    let mut evict_interval = tokio::time::interval(Duration::from_secs(60));
+
+    loop {
+        let notification = tokio::select! {
+            notification = listener.try_recv() => notification?,
+            _ = evict_interval.tick() => {
+                let ttl = Duration::from_secs(global_data.config.read().auth_cache_ttl);
+                global_data.auth_cache.evict_expired(ttl);
+                continue;
+            }
+        };
+
+        let Some(notification) = notification else {
+            bail!("disconnected");
+        };
+
+        match notification.channel() {
+            AUTH_CHANGE_CHANNEL => match notification.payload().parse::<i32>() {
+                Ok(user_id) => global_data.auth_cache.invalidate_user(user_id),
+                Err(_) => {
+                    warn!("Invalid auth change notification: {}", notification.payload());
+                    global_data.auth_cache.clear();
+                }
+            },
+            ACCOUNT_CHANGE_CHANNEL => match notification.payload().parse::<i32>() {
+                Ok(account_id) => {
+                    if let Err(e) = refresh_account(global_data, account_id).await {
+                        error!("Error when refresh account {}: {}", account_id, e);
+                    }
+                }
+                Err(_) => warn!("Invalid account change notification: {}", notification.payload()),
+            },
+            MASTER_KEY_CHANGE_CHANNEL => reload_master_key(global_data).await,
+            channel => warn!("Unknown database notification channel: {}", channel),
+        }
+    }
+}
@@ -4,6 +4,7 @@ use sqlx_postgres::{PgPoolOptions, Postgres};
 use crate::data::config::entity::config_file::Config;
 
 /// Connect to the database, I am use PostgreSQL in the debug environment.
+/// The migrations in `sql/migrations` will be applied after connected.
 /// # Arguments
 /// - config: The config of the server.
 pub async fn connect_to_database_sqlx(config: &Config) -> anyhow::Result<Pool<Postgres>> {
@@ -16,5 +17,8 @@ pub async fn connect_to_database_sqlx(config: &Config) -> anyhow::Result<Pool<Po
     let pool = PgPoolOptions::new()
         .connect(string.as_str()).await?;
 
+    // Apply the schema changes after init.sql
+    sqlx::migrate!("./sql/migrations").run(&pool).await?;
+
     Ok(pool)
 }
\ No newline at end of file
@@ -2,6 +2,34 @@ pub struct DataBaseAccount {
     pub id: i32,
     pub is_disabled: bool,
     pub use_proxy: Option<String>,
-    pub api_key: String,
-    pub endpoint: String
+    pub api_key: Option<String>,
+    pub endpoint: String,
+    pub encrypted_key: Option<String>,
+    pub data_key: Option<String>,
+    pub max_concurrency: Option<i32>,
+    pub monthly_budget: Option<rust_decimal::Decimal>,
+    pub budget_exceeded_month: Option<time::Date>,
+}
+
+/// The settings of an account that its visitor is built from, the pool of an account is
+/// only rebuilt when they are changed, so that its slots and stats are kept.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountSettings {
+    pub endpoint: String,
+    pub use_proxy: Option<String>,
+    pub encrypted_key: Option<String>,
+    pub data_key: Option<String>,
+    pub max_concurrency: Option<i32>,
+}
+
+impl DataBaseAccount {
+    pub fn settings(&self) -> AccountSettings {
+        AccountSettings {
+            endpoint: self.endpoint.clone(),
+            use_proxy: self.use_proxy.clone(),
+            encrypted_key: self.encrypted_key.clone(),
+            data_key: self.data_key.clone(),
+            max_concurrency: self.max_concurrency,
+        }
+    }
 }
\ No newline at end of file
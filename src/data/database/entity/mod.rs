@@ -4,4 +4,6 @@ pub mod data_base_account;
 pub mod usage_list;
 pub mod user_command;
 pub mod user;
-pub mod user_usage;
\ No newline at end of file
+pub mod user_usage;
+pub mod user_group;
+pub mod user_key;
\ No newline at end of file
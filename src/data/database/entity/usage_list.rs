@@ -5,5 +5,14 @@ pub struct DataBaseUsageList {
     pub input_tokens: i32,
     pub output_tokens: i32,
     pub input_token_price: f64,
-    pub output_token_price: f64
+    pub output_token_price: f64,
+    pub account_id: Option<i32>,
+    pub endpoint: Option<String>,
+    pub model: Option<String>,
+    pub cost: Option<f64>,
+    pub cached_input_tokens: i32,
+    pub cached_input_token_price: f64,
+    pub reasoning_tokens: i32,
+    pub reasoning_token_price: f64,
+    pub amount: f64,
 }
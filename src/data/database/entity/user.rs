@@ -1,6 +1,6 @@
 #[derive(Debug)]
 pub struct DataBaseUser {
     pub id: i32,
-    pub api_key: String,
-    pub is_active: bool
+    pub is_active: bool,
+    pub group_id: Option<i32>,
 }
\ No newline at end of file
@@ -0,0 +1,6 @@
+pub struct DataBaseUserGroup {
+    pub id: i32,
+    pub name: String,
+    pub allowed_models: Vec<String>,
+    pub priority: i32,
+}
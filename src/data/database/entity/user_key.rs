@@ -0,0 +1,15 @@
+use time::PrimitiveDateTime;
+
+#[derive(Debug)]
+pub struct DataBaseUserKey {
+    pub id: i32,
+    pub user_id: i32,
+    pub key_prefix: String,
+    pub key_salt: String,
+    pub key_hash: String,
+    pub label: String,
+    pub created_at: PrimitiveDateTime,
+    pub expires_at: Option<PrimitiveDateTime>,
+    pub last_used_at: Option<PrimitiveDateTime>,
+    pub revoked_at: Option<PrimitiveDateTime>,
+}
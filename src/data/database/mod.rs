@@ -1,2 +1,6 @@
 pub mod database_manager;
 pub mod entity;
+pub mod account_secret;
+pub mod auth_cache;
+pub mod change_listener;
+pub mod user_key;
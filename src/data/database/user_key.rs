@@ -0,0 +1,202 @@
+//! The api keys of the users.
+//! The keys are never stored in plain text, only a short prefix of the key is stored
+//! for lookup and display, with the salted SHA-256 hash of the key:
+//! `key_hash = hex(sha256(key_salt || api_key))`.
+//! So the key can only be shown once when it is created.
+
+use anyhow::anyhow;
+use sha2::{Digest, Sha256};
+use sqlx::Pool;
+use sqlx_postgres::{PgExecutor, Postgres};
+use uuid::Uuid;
+
+use crate::data::database::entity::user::DataBaseUser;
+use crate::data::database::entity::user_key::DataBaseUserKey;
+use crate::data::config::entity::model_access::ModelAllowList;
+
+/// The length of the visible prefix of the key, e.g. `sk-1a2b3c4d`.
+const KEY_PREFIX_LENGTH: usize = 11;
+
+/// The owner of a key, which is resolved when a request is coming.
+/// # Fields
+/// - key_id: The id of the key.
+/// - user_id: The id of the user who owns the key.
+/// - is_active: Whether the user is active.
+/// - expired: Whether the key is expired.
+/// - revoked: Whether the key is revoked.
+/// - priority: The priority of the user's group, `0` if the user has no group.
+/// - allow_list: The models allowed by the user's group.
+#[derive(Debug, Clone)]
+pub struct KeyOwner {
+    pub key_id: i32,
+    pub user_id: i32,
+    pub is_active: bool,
+    pub expired: bool,
+    pub revoked: bool,
+    pub priority: i32,
+    pub allow_list: ModelAllowList,
+}
+
+/// The keys found by the prefix, which should be verified by the hash.
+struct KeyCandidate {
+    key_id: i32,
+    user_id: i32,
+    key_salt: String,
+    key_hash: String,
+    is_active: bool,
+    expired: bool,
+    revoked: bool,
+    priority: i32,
+    allowed_models: Option<Vec<String>>,
+}
+
+/// Find the owner of the key, `None` if the key does not exist.
+pub async fn find_key_owner(data_base: &Pool<Postgres>, key: &str) -> anyhow::Result<Option<KeyOwner>> {
+    let candidates = sqlx::query_as!(
+        KeyCandidate,
+        r#"SELECT
+            k.id AS key_id,
+            k.user_id,
+            k.key_salt,
+            k.key_hash,
+            u.is_active,
+            (k.expires_at IS NOT NULL AND k.expires_at <= NOW()::TIMESTAMP) AS "expired!",
+            (k.revoked_at IS NOT NULL) AS "revoked!",
+            COALESCE(g.priority, 0) AS "priority!",
+            g.allowed_models AS "allowed_models?"
+        FROM user_key k
+        JOIN "user" u ON u.id = k.user_id
+        LEFT JOIN user_group g ON g.id = u.group_id
+        WHERE k.key_prefix = $1"#,
+        key_prefix(key)
+    )
+        .fetch_all(data_base)
+        .await?;
+
+    let owner = candidates
+        .into_iter()
+        .find(|x| constant_time_eq(hash_key(&x.key_salt, key).as_bytes(), x.key_hash.as_bytes()))
+        .map(|x| KeyOwner {
+            key_id: x.key_id,
+            user_id: x.user_id,
+            is_active: x.is_active,
+            expired: x.expired,
+            revoked: x.revoked,
+            priority: x.priority,
+            allow_list: ModelAllowList::new(x.allowed_models),
+        });
+
+    Ok(owner)
+}
+
+/// Record the last used time of the key, it will be updated at most once a minute.
+pub async fn touch_key(data_base: &Pool<Postgres>, key_id: i32) -> anyhow::Result<()> {
+    sqlx::query!(
+        r#"UPDATE user_key SET last_used_at = NOW()::TIMESTAMP
+        WHERE id = $1 AND (last_used_at IS NULL OR last_used_at < NOW()::TIMESTAMP - INTERVAL '1 minute')"#,
+        key_id
+    )
+        .execute(data_base)
+        .await?;
+
+    Ok(())
+}
+
+/// Find the user by the key, the key is found even if it is expired or revoked.
+pub async fn find_user_by_key(data_base: &Pool<Postgres>, key: &str) -> anyhow::Result<DataBaseUser> {
+    let owner = find_key_owner(data_base, key)
+        .await?
+        .ok_or_else(|| anyhow!("User with key {} not found", mask_key(key)))?;
+
+    sqlx::query_as!(DataBaseUser, r#"SELECT * FROM "user" WHERE id = $1"#, owner.user_id)
+        .fetch_one(data_base)
+        .await
+        .map_err(Into::into)
+}
+
+/// Find the user by the key or the id of the user.
+pub async fn find_user(data_base: &Pool<Postgres>, key_or_id: &str) -> anyhow::Result<DataBaseUser> {
+    if key_or_id.starts_with("sk-") {
+        return find_user_by_key(data_base, key_or_id).await;
+    }
+
+    let id = key_or_id
+        .parse::<i32>()
+        .map_err(|_| anyhow!("Invalid user: must be an api key starts with 'sk-' or a user id"))?;
+
+    sqlx::query_as!(DataBaseUser, r#"SELECT * FROM "user" WHERE id = $1"#, id)
+        .fetch_optional(data_base)
+        .await?
+        .ok_or_else(|| anyhow!("User {} not found", id))
+}
+
+/// Create a new key for the user, a random key will be generated if `key` is `None`.
+/// The plain key is returned with the record, it can not be found again after this.
+/// # Arguments
+/// - label: The label of the key, e.g. the name of the app that uses it.
+/// - expires_in_days: The key will expire after these days, never expire if `None`.
+pub async fn create_key<'e>(
+    executor: impl PgExecutor<'e>,
+    user_id: i32,
+    key: Option<String>,
+    label: &str,
+    expires_in_days: Option<i32>,
+) -> anyhow::Result<(String, DataBaseUserKey)> {
+    let key = key.unwrap_or_else(generate_key);
+    let salt = Uuid::new_v4().simple().to_string();
+
+    let record = sqlx::query_as!(
+        DataBaseUserKey,
+        r#"INSERT INTO user_key (user_id, key_prefix, key_salt, key_hash, label, expires_at)
+        VALUES ($1, $2, $3, $4, $5, NOW()::TIMESTAMP + make_interval(days => $6))
+        RETURNING *"#,
+        user_id,
+        key_prefix(&key),
+        salt,
+        hash_key(&salt, &key),
+        label,
+        expires_in_days
+    )
+        .fetch_one(executor)
+        .await?;
+
+    Ok((key, record))
+}
+
+/// Generate a random key, which starts with `sk-`.
+pub fn generate_key() -> String {
+    let base = Uuid::new_v4().to_string().replace("-", "");
+    let extra = Uuid::new_v4().to_string();
+    format!(
+        "sk-{}{}{}{}",
+        base,
+        &extra[0..8],
+        &extra[9..13],
+        &extra[19..23]
+    )
+}
+
+/// Mask the key for display, only the prefix of the key is shown.
+pub fn mask_key(key: &str) -> String {
+    format!("{}...", key_prefix(key))
+}
+
+/// The visible prefix of the key.
+pub fn key_prefix(key: &str) -> &str {
+    key.char_indices()
+        .nth(KEY_PREFIX_LENGTH)
+        .map_or(key, |(index, _)| &key[..index])
+}
+
+fn hash_key(salt: &str, key: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(salt.as_bytes());
+    hasher.update(key.as_bytes());
+
+    format!("{:x}", hasher.finalize())
+}
+
+/// Compare the hashes in constant time, so the hash can not be guessed by timing.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -18,4 +18,6 @@ pub struct Input {
 pub struct Parameters {
     pub incremental_output: Option<bool>,
     pub result_format: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
 }
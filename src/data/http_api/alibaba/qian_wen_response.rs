@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::data::config::entity::model_price::TokenUsage;
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct QianWenResponse {
@@ -30,4 +32,41 @@ pub struct Usage {
     pub total_tokens: i64,
     pub input_tokens: i64,
     pub output_tokens: i64,
+    #[serde(default)]
+    pub prompt_tokens_details: Option<PromptTokensDetails>,
+    #[serde(default)]
+    pub output_tokens_details: Option<OutputTokensDetails>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PromptTokensDetails {
+    pub cached_tokens: i64,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputTokensDetails {
+    pub reasoning_tokens: i64,
+}
+
+impl Usage {
+    /// The usage reported by the endpoint, `None` if the endpoint does not report it.
+    /// The cached tokens are part of the input tokens, and the reasoning tokens are part
+    /// of the output tokens.
+    pub fn token_usage(&self) -> Option<TokenUsage> {
+        if self.input_tokens <= 0 && self.output_tokens <= 0 {
+            return None;
+        }
+
+        let cached = self.prompt_tokens_details.as_ref().map_or(0, |x| x.cached_tokens);
+        let reasoning = self.output_tokens_details.as_ref().map_or(0, |x| x.reasoning_tokens);
+
+        Some(TokenUsage {
+            input: (self.input_tokens - cached).max(0) as u64,
+            cached_input: cached.max(0) as u64,
+            output: (self.output_tokens - reasoning).max(0) as u64,
+            reasoning: reasoning.max(0) as u64,
+        })
+    }
 }
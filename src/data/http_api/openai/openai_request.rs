@@ -18,6 +18,8 @@ pub struct OpenAIRequest {
     pub stream: Option<bool>,
     pub max_tokens: Option<u32>,
     pub tools: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<Value>,
 }
 
 impl OpenAIRequest {
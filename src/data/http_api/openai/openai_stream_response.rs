@@ -2,6 +2,8 @@ use std::time::{SystemTime, UNIX_EPOCH};
 
 use serde::{Deserialize, Serialize};
 
+use crate::data::http_api::openai::openai_sync_response::Usage;
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct OpenAIStreamResponse {
@@ -11,6 +13,8 @@ pub struct OpenAIStreamResponse {
     pub model: Option<String>,
     pub system_fingerprint: Option<String>,
     pub choices: Vec<Choice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -48,6 +52,7 @@ impl OpenAIStreamResponse {
                 logprobs: None,
                 finish_reason: if end { Some("stop".to_string()) } else { None },
             }],
+            usage: None,
         }
     }
 }
@@ -2,6 +2,8 @@ use std::time::{SystemTime, UNIX_EPOCH};
 
 use serde::{Deserialize, Serialize};
 
+use crate::data::config::entity::model_price::TokenUsage;
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct OpenAISyncResponse {
     pub id: Option<String>,
@@ -32,6 +34,43 @@ pub struct Usage {
     pub prompt_tokens: i64,
     pub completion_tokens: i64,
     pub total_tokens: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_tokens_details: Option<PromptTokensDetails>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completion_tokens_details: Option<CompletionTokensDetails>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PromptTokensDetails {
+    pub cached_tokens: i64,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompletionTokensDetails {
+    pub reasoning_tokens: i64,
+}
+
+impl Usage {
+    /// The usage reported by the endpoint, `None` if the endpoint does not report it.
+    /// The cached tokens are part of the prompt tokens, and the reasoning tokens are part
+    /// of the completion tokens.
+    pub fn token_usage(&self) -> Option<TokenUsage> {
+        if self.prompt_tokens <= 0 && self.completion_tokens <= 0 {
+            return None;
+        }
+
+        let cached = self.prompt_tokens_details.as_ref().map_or(0, |x| x.cached_tokens);
+        let reasoning = self.completion_tokens_details.as_ref().map_or(0, |x| x.reasoning_tokens);
+
+        Some(TokenUsage {
+            input: (self.prompt_tokens - cached).max(0) as u64,
+            cached_input: cached.max(0) as u64,
+            output: (self.completion_tokens - reasoning).max(0) as u64,
+            reasoning: reasoning.max(0) as u64,
+        })
+    }
 }
 
 impl OpenAISyncResponse {
@@ -1,19 +1,26 @@
+use std::fmt::Display;
 use std::ops::Deref;
+use std::pin::pin;
+use std::time::{Duration, Instant};
 
 use colored::Colorize;
 use log::{error, info};
 
 use crate::data::config::entity::endpoint::Endpoint;
-use crate::data::config::entity::runtime_data::{AccountVisitor, GlobalData};
+use crate::data::config::entity::runtime_data::{AccountPool, AccountVisitor, GlobalData};
 use crate::data::http_api::openai::openai_request::{MessageLocation, MessageUtil};
 use crate::http::client::client_sender::channel_manager::{
     ChannelSender, ClientSender, ResponsiveError,
 };
 use crate::http::client::specific_responder::{ResponderError, SpecificResponder};
-use crate::http::client::util::counter::concurrency_pool::{SafeObject, SafePool, VecGettable};
+use crate::http::client::util::affinity::conversation_key;
+use crate::http::client::util::counter::concurrency_pool::{SafeObject, VecGettable};
+use crate::http::metrics::{model_label, REQUESTS_TOTAL, REQUEST_DURATION, RETRIES_TOTAL, TIME_TO_FIRST_TOKEN};
+
+/// The interval of the heartbeat sent to the stream client while it is queued.
+const QUEUE_KEEP_ALIVE: Duration = Duration::from_secs(10);
 
 /// The response data from the responder
-#[allow(dead_code)]
 pub struct ResponseData {
     pub account_id: i32,
     pub use_endpoint: Endpoint,
@@ -23,35 +30,40 @@ impl GlobalData {
     /// Try to request the endpoint with the sender
     /// # Arguments
     /// * `sender` - The sender that send the request
+    /// * `user_id` - The user of the request, used by the fair queue
+    /// * `priority` - The priority of the group of the user, the requests with a priority
+    ///   greater than 0 can use the reserved slots of the accounts
     /// # Returns
     /// * `Option<ResponseData>` - The response data from the responder
     /// * `None` - If the request failed
-    pub async fn try_request(&self, sender: &mut ClientSender) -> Option<ResponseData> {
-        let account_pool = self.account_pool.read();
-        let mut account = match Self::get_account(sender, &self, account_pool.deref()).await {
+    pub async fn try_request(&self, sender: &mut ClientSender, user_id: i32, priority: i32) -> Option<ResponseData> {
+        // Only used as the label of the metrics.
+        let request_model = model_label(self, &sender.request.model).to_string();
+        let affinity_ttl = Duration::from_secs(self.config.read().affinity_ttl);
+        let affinity_key = (!affinity_ttl.is_zero())
+            .then(|| conversation_key(user_id, &sender.request.messages));
+        let affinity = affinity_key.as_ref().and_then(|x| self.affinity.get(x, affinity_ttl));
+
+        // The snapshot is kept until the request ends, so the changes of the pool are not
+        // blocked by the running requests.
+        let account_pool = self.account_pool.load_full();
+        let mut account = match Self::get_account(sender, &self, account_pool.deref(), user_id, priority, affinity, &[]).await {
             Ok(ok) => ok,
             Err(err) => {
-                sender.append_error(ResponsiveError {
-                    component: "上游账户池".to_string(),
-                    reason: "获取上游失败".to_string(),
-                    message: "无法从账户池中读取上游账户信息信息".to_string(),
-                    suggestion: Some(
-                        "当前账户池无法响应您的请求，请联系我们或稍候重试。".to_string(),
-                    ),
-                });
-                error!("Error when get account visitor: {}", err);
+                account_error(sender, &request_model, err).await;
                 return None;
             }
         };
-        info!("Use of model: {}", sender.request.model);
+        info!("[{}] Use of model: {}", sender.request_id, sender.request.model);
 
-        let mut account_count = {
+        let (mut account_count, retry_status) = {
             let guard = self.config.read();
-            guard.number_can_retries
+            (guard.number_can_retries, guard.retry_status.clone())
         };
 
         info!(
-            "Account with id: {}({}) {}: {:?}",
+            "[{}] Account with id: {}({}) {}: {:?}",
+            sender.request_id,
             account.account_id.to_string().blue(),
             account.endpoint,
             "start with prompt".yellow(),
@@ -61,19 +73,88 @@ impl GlobalData {
                 .get_user_input(MessageLocation::LAST)
         );
 
-        // Apply the model mapping
-        let model_mapping = self.model_mapping.read();
-        if let Some(model) = model_mapping.get(&account.endpoint) {
-            if let Some(model_name) = model.get(&sender.request.model) {
-                info!("Apply model mapping: {} -> {}", sender.request.model, model_name);
-                sender.request.model = model_name.to_string();
+        // Apply the model mapping, the lock is released before the request is sent.
+        {
+            let model_mapping = self.model_mapping.read();
+            if let Some(model) = model_mapping.get(&account.endpoint) {
+                if let Some(model_name) = model.get(&sender.request.model) {
+                    info!("[{}] Apply model mapping: {} -> {}", sender.request_id, sender.request.model, model_name);
+                    sender.request.model = model_name.to_string();
+                }
             }
         }
-        drop(model_mapping);
 
+        // The accounts failed for this request, which are avoided by the retries.
+        let mut tried = Vec::new();
         loop {
-            match account.responder.make_response(sender, *account).await {
+            sender.reset_first_send();
+            sender.reset_usage();
+            let start = Instant::now();
+            let result = account.responder.make_response(sender, *account).await;
+            let failed = match &result {
+                Err(ResponderError::Request(_)) => true,
+                Err(ResponderError::Status { status, .. }) => is_retryable(*status, &retry_status),
+                _ => false,
+            };
+            observe_latency(*account, sender, start, failed);
+
+            match result {
+                // The response has been partly sent, so it can not be retried on another account.
+                Err(err @ (ResponderError::Request(_) | ResponderError::Status { .. }))
+                    if sender.first_send().is_some() =>
+                {
+                    error!(
+                        "[{}] Error after the response has been sent on {}: {}",
+                        sender.request_id, account.endpoint, err
+                    );
+                    sender.append_error(ResponsiveError {
+                        component: "代理器核心".to_string(),
+                        reason: "响应中断".to_string(),
+                        message: format!("上游在响应过程中出错：{}", err),
+                        suggestion: Some("回复可能不完整，请重新发起请求".to_string()),
+                    });
+                    if let Err(send_error) = sender.send_error().await {
+                        error!("[{}] Error when send error message: {}", sender.request_id, send_error);
+                    }
+
+                    count_request(&request_model, &account.endpoint.to_string(), "upstream_error");
+                    break Some(ResponseData {
+                        account_id: account.account_id,
+                        use_endpoint: account.endpoint.clone(),
+                    });
+                }
+                // The request will fail on every account, so the error of the endpoint is returned at once.
+                Err(ResponderError::Status { status, body }) if !is_retryable(status, &retry_status) => {
+                    error!(
+                        "[{}] Request rejected by {} with code {}: {}",
+                        sender.request_id, account.endpoint, status, body
+                    );
+                    sender.append_error(ResponsiveError {
+                        component: "上游服务".to_string(),
+                        reason: format!("请求被上游拒绝（{}）", status),
+                        message: body,
+                        suggestion: Some("请检查请求内容，例如上下文长度是否超出了模型的限制".to_string()),
+                    });
+                    if let Err(send_error) = sender.send_error().await {
+                        error!("[{}] Error when send error message: {}", sender.request_id, send_error);
+                    }
+
+                    count_request(&request_model, &account.endpoint.to_string(), "rejected_upstream");
+                    return None;
+                }
                 Err(err) => match err {
+                    ResponderError::Status { status, body } => {
+                        sender.append_error(ResponsiveError {
+                            component: "代理器核心".to_string(),
+                            reason: format!("上游服务异常（{}）", status),
+                            message: format!("请求服务失败：{}", body),
+                            suggestion: None,
+                        });
+                        error!(
+                            "[{}] Error when make request on {} with code {}: {}, try again with count {}.",
+                            sender.request_id, account.endpoint, status, body, account_count
+                        );
+                    }
                     ResponderError::Request(err) => {
                         sender.append_error(ResponsiveError {
                             component: "代理器核心".to_string(),
@@ -82,29 +163,37 @@ impl GlobalData {
                             suggestion: None,
                         });
                         error!(
-                            "Error when make request on {}: {}, try again with count {}.",
-                            account.endpoint, err, account_count
+                            "[{}] Error when make request on {}: {}, try again with count {}.",
+                            sender.request_id, account.endpoint, err, account_count
                         );
                     }
                     ResponderError::Response(err) => {
                         error!(
-                            "Success get message, but error when send to client: {}",
+                            "[{}] Success get message, but error when send to client: {}",
+                            sender.request_id,
                             err.red()
                         );
+                        count_request(&request_model, &account.endpoint.to_string(), "client_error");
                         break Some(ResponseData {
                             account_id: account.account_id,
                             use_endpoint: account.endpoint.clone(),
                         });
                     }
                 },
-                Ok(_) => break Some(ResponseData {
-                    account_id: account.account_id,
-                    use_endpoint: account.endpoint.clone(),
-                }),
+                Ok(_) => {
+                    count_request(&request_model, &account.endpoint.to_string(), "success");
+                    if let Some(key) = affinity_key {
+                        self.affinity.insert(key, account.account_id);
+                    }
+                    break Some(ResponseData {
+                        account_id: account.account_id,
+                        use_endpoint: account.endpoint.clone(),
+                    });
+                }
             }
 
             account_count -= 1;
-            if account_count <= 0 {
+            if account_count == 0 {
                 sender.append_error(ResponsiveError {
                     component: "代理器核心".to_string(),
                     reason: "请求失败".to_string(),
@@ -116,53 +205,181 @@ impl GlobalData {
                 });
 
                 if let Err(send_error) = sender.send_error().await {
-                    error!("Error when send error message: {}", send_error);
+                    error!("[{}] Error when send error message: {}", sender.request_id, send_error);
                 }
 
+                count_request(&request_model, &account.endpoint.to_string(), "upstream_error");
+
                 break Some(ResponseData {
                     account_id: account.account_id,
                     use_endpoint: account.endpoint.clone(),
                 });
             }
 
-            account = match Self::get_account(sender, &self, account_pool.deref()).await {
+            RETRIES_TOTAL
+                .with_label_values(&[request_model.as_str(), account.endpoint.to_string().as_str()])
+                .inc();
+
+            // Release the failed account before waiting, so it is not blocked by the retry.
+            tried.push(account.account_id);
+            drop(account);
+
+            account = match Self::get_account(sender, &self, account_pool.deref(), user_id, priority, None, &tried).await {
                 Ok(ok) => ok,
                 Err(err) => {
-                    sender.append_error(ResponsiveError {
-                        component: "上游账户池".to_string(),
-                        reason: "获取上游失败".to_string(),
-                        message: "无法从账户池中读取上游账户信息信息".to_string(),
-                        suggestion: Some(
-                            "当前账户池无法响应您的请求，请联系我们或稍候重试。".to_string(),
-                        ),
-                    });
-                    error!("Error when get account visitor: {}", err);
+                    account_error(sender, &request_model, err).await;
                     return None;
                 }
             }
         }
     }
 
+    /// Get a free account for the request.
+    /// The request gets a free account at once if no one of the same priority is queued,
+    /// otherwise it joins the lane of its priority in the fair queue and waits at most
+    /// `queue_max_wait` seconds, the stream client receives a heartbeat while waiting.
+    /// The account in `affinity` is preferred if it is free and healthy, the accounts in `tried`
+    /// are only used when no other account supports the model.
     async fn get_account<'a>(
         sender: &ClientSender,
         data: &'a GlobalData,
-        pool: &'a Vec<SafePool<AccountVisitor>>,
-    ) -> Result<SafeObject<'a, &'a AccountVisitor>, String> {
-        let mut try_count = 0_u8;
-        let model_info = data.model_info.read();
+        pool: &'a AccountPool,
+        user_id: i32,
+        priority: i32,
+        affinity: Option<i32>,
+        tried: &[i32],
+    ) -> Result<SafeObject<'a, &'a AccountVisitor>, AccountError> {
+        let model = sender.request.model.as_str();
+        let high_priority = priority > 0;
+        let available = |x: &AccountVisitor| data.model_info.read().check_available(&x.endpoint, model);
+
+        if !pool.iter().any(|x| available(x.get_visitor())) {
+            return Err(AccountError::NoAccount);
+        }
+
+        let untried_left = pool
+            .iter()
+            .any(|x| available(x.get_visitor()) && !tried.contains(&x.get_account_id()));
+        let filter = |x: &AccountVisitor| available(x) && (!untried_left || !tried.contains(&x.account_id));
 
+        if data.request_queue.is_lane_empty(priority) {
+            let preferred = |x: &AccountVisitor| affinity == Some(x.account_id) && x.stats.is_healthy() && filter(x);
+            if affinity.is_some()
+                && let Some(account) = pool.get_preferred_object(model, preferred, high_priority)
+            {
+                info!("[{}] Use the account {} of the previous turn.", sender.request_id, account.account_id);
+                return Ok(account);
+            }
+
+            if let Some(account) = pool.get_safe_object(model, &filter, Duration::ZERO, high_priority).await {
+                return Ok(account);
+            }
+        }
+
+        let (capacity, max_wait) = {
+            let config = data.config.read();
+            (config.queue_capacity, Duration::from_secs(config.queue_max_wait))
+        };
+
+        let Some(ticket) = data.request_queue.enter(user_id, priority, capacity) else {
+            return Err(AccountError::QueueFull);
+        };
+        info!("[{}] All accounts are busy, queued with {} requests.", sender.request_id, data.request_queue.len());
+
+        let deadline = tokio::time::Instant::now() + max_wait;
+        let mut wait = pin!(async {
+            ticket.wait_turn().await;
+            let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+            pool.get_safe_object(model, &filter, remaining, high_priority).await
+        });
+
+        let mut keep_alive = tokio::time::interval_at(tokio::time::Instant::now() + QUEUE_KEEP_ALIVE, QUEUE_KEEP_ALIVE);
         loop {
-            if let Some(account) = pool.get_safe_object().await {
-                if model_info.check_available(&account.endpoint, &sender.request.model) {
-                    return Ok(account);
+            tokio::select! {
+                account = &mut wait => return account.ok_or(AccountError::Timeout),
+                _ = tokio::time::sleep_until(deadline) => return Err(AccountError::Timeout),
+                _ = keep_alive.tick() => {
+                    if let Err(e) = sender.send_keep_alive().await {
+                        error!("[{}] Error when send heartbeat to client: {}", sender.request_id, e);
+                    }
                 }
             }
+        }
+    }
+}
 
-            if try_count >= 30 {
-                return Err("Can't find available account!".to_string());
-            }
+/// The reason why no account can be got for the request.
+#[derive(Debug)]
+enum AccountError {
+    NoAccount,
+    QueueFull,
+    Timeout,
+}
 
-            try_count += 1;
+impl Display for AccountError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccountError::NoAccount => write!(f, "No account supports the model"),
+            AccountError::QueueFull => write!(f, "The request queue is full"),
+            AccountError::Timeout => write!(f, "No account is free in time"),
         }
     }
 }
+
+/// Tell the client why no account can be got.
+async fn account_error(sender: &mut ClientSender, model: &str, err: AccountError) {
+    let (outcome, reason, suggestion) = match err {
+        AccountError::QueueFull => (
+            "queue_full",
+            "排队人数已满",
+            "当前请求过多，请稍候重试。",
+        ),
+        AccountError::NoAccount | AccountError::Timeout => (
+            "no_account",
+            "获取上游失败",
+            "当前账户池无法响应您的请求，请联系我们或稍候重试。",
+        ),
+    };
+
+    count_request(model, "none", outcome);
+    sender.append_error(ResponsiveError {
+        component: "上游账户池".to_string(),
+        reason: reason.to_string(),
+        message: "无法从账户池中读取上游账户信息信息".to_string(),
+        suggestion: Some(suggestion.to_string()),
+    });
+    error!("[{}] Error when get account visitor: {}", sender.request_id, err);
+
+    if let Err(send_error) = sender.send_error().await {
+        error!("[{}] Error when send error message: {}", sender.request_id, send_error);
+    }
+}
+
+/// Check if the error status of the endpoint may be fixed by another account, the 5xx codes
+/// are always retried.
+fn is_retryable(status: u16, retry_status: &[u16]) -> bool {
+    status >= 500 || retry_status.contains(&status)
+}
+
+/// Count the request by model, endpoint and outcome.
+fn count_request(model: &str, endpoint: &str, outcome: &str) {
+    REQUESTS_TOTAL.with_label_values(&[model, endpoint, outcome]).inc();
+}
+
+/// Record the time to first token and the total latency of an attempt on the account.
+/// The stats of the account are also updated, only the time to first token of a stream
+/// response is used, since a sync response is sent after it is finished.
+fn observe_latency(account: &AccountVisitor, sender: &ClientSender, start: Instant, failed: bool) {
+    let labels = [account.account_id.to_string(), account.endpoint.to_string()];
+    let ttft = sender.first_send().map(|x| x.duration_since(start));
+    if let Some(ttft) = ttft {
+        TIME_TO_FIRST_TOKEN
+            .with_label_values(&labels)
+            .observe(ttft.as_secs_f64());
+    }
+    account.stats.record(ttft.filter(|_| sender.is_stream()), failed);
+
+    REQUEST_DURATION
+        .with_label_values(&labels)
+        .observe(start.elapsed().as_secs_f64());
+}
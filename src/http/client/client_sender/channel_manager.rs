@@ -1,9 +1,11 @@
+use crate::data::config::entity::model_price::TokenUsage;
 use crate::data::http_api::openai::openai_request::OpenAIRequest;
 use crate::data::http_api::openai::openai_stream_response::OpenAIStreamResponse;
 use crate::data::http_api::openai::openai_sync_response::OpenAISyncResponse;
 use anyhow::Result;
 use log::{debug, error, info};
 use ntex::util::Bytes;
+use std::sync::OnceLock;
 use std::time::{Duration, Instant};
 use tokio::spawn;
 use tokio::sync::mpsc::Sender;
@@ -25,13 +27,18 @@ pub struct ResponsiveError {
 
 pub type ClientSenderInner = Sender<Bytes>;
 
+/// The empty chunk that keeps the stream alive, which is ignored by the clients.
+const HEARTBEAT: &str = concat!(r#"data:  {"id":"chatcmpl-9709rQdvMSIASrvcWGVsJMQouP2UV","object":"chat.completion.chunk","created":1746818209,"model":"heartbeat","system_fingerprint":"fp_3bc1b5746c","choices":[{"index":0,"delta":{"content":""},"logprobs":null,"finish_reason":null}]}"#, "\n\n");
+
 /// This struct represents a channel that is used to communicate with the client.
 /// # Fields
 /// * `inner` - The sender that is used to send messages to the client.
 /// * `error_message` - A list of error messages that have occurred while processing the request.
 /// * `buffer` - A buffer that is used to store messages that are sent to the client.
 /// * `request` - The request that is sending from client.
+/// * `request_id` - The id of the request, which is attached to every log line of this request.
 /// * `is_stream` - A flag that indicates whether the request is a stream request.
+/// * `usage` - The tokens used by the request reported by the endpoint, if it reports them.
 #[derive(Debug)]
 pub struct ClientSender {
     inner: ClientSenderInner,
@@ -39,13 +46,16 @@ pub struct ClientSender {
     buffer: String,
     is_empty: bool,
     last_activity: &'static mut Mutex<Instant>,
+    first_send: OnceLock<Instant>,
+    usage: Option<TokenUsage>,
 
     pub stopped: bool,
     pub request: OpenAIRequest,
+    pub request_id: String,
 }
 
 impl ClientSender {
-    pub fn new(inner: ClientSenderInner, request: OpenAIRequest) -> Self {
+    pub fn new(inner: ClientSenderInner, request: OpenAIRequest, request_id: String) -> Self {
         let last_activity = Box::leak(Box::new(Mutex::new(Instant::now())));
         if request.is_stream() {
             let sender_weak = inner.downgrade();
@@ -53,6 +63,7 @@ impl ClientSender {
             let last_activity_clone = unsafe {
                 &mut *(last_activity_clone as *mut Mutex<Instant>)
             };
+            let request_id = request_id.clone();
 
             // 启动心跳检查任务
             debug!("Every sender will keep alive for 25 seconds");
@@ -69,11 +80,9 @@ impl ClientSender {
                         };
 
                         if should_send_heartbeat {
-                            info!("Send heartbeat to client");
-                            if let Err(e) = sender.send(Bytes::from(
-                                concat!(r#"data:  {"id":"chatcmpl-9709rQdvMSIASrvcWGVsJMQouP2UV","object":"chat.completion.chunk","created":1746818209,"model":"heartbeat","system_fingerprint":"fp_3bc1b5746c","choices":[{"index":0,"delta":{"content":""},"logprobs":null,"finish_reason":null}]}"#, "\n\n")
-                            )).await {
-                                error!("Error when send heartbeat to client: {}", e);
+                            info!("[{}] Send heartbeat to client", request_id);
+                            if let Err(e) = sender.send(Bytes::from(HEARTBEAT)).await {
+                                error!("[{}] Error when send heartbeat to client: {}", request_id, e);
                                 break;
                             }
                             *last = Instant::now();
@@ -94,11 +103,14 @@ impl ClientSender {
         Self {
             inner,
             request,
+            request_id,
             is_empty: true,
             stopped: false,
             buffer: String::new(),
             error_message: Vec::new(),
             last_activity,
+            first_send: OnceLock::new(),
+            usage: None,
         }
     }
 
@@ -113,6 +125,50 @@ impl ClientSender {
     pub fn not_empty(&mut self) {
         self.is_empty = false;
     }
+
+    pub fn has_error(&self) -> bool {
+        !self.error_message.is_empty()
+    }
+
+    /// The instant when the first message was sent to the client in the current attempt.
+    pub fn first_send(&self) -> Option<Instant> {
+        self.first_send.get().copied()
+    }
+
+    /// Reset the first send instant, should be called before every attempt.
+    pub fn reset_first_send(&mut self) {
+        self.first_send.take();
+    }
+
+    /// The tokens used by the request reported by the endpoint.
+    pub fn usage(&self) -> Option<TokenUsage> {
+        self.usage
+    }
+
+    /// Record the usage reported by the endpoint, the later one replaces the former, since
+    /// the stream endpoints report the accumulated usage.
+    pub fn set_usage(&mut self, usage: Option<TokenUsage>) {
+        if usage.is_some() {
+            self.usage = usage;
+        }
+    }
+
+    /// Forget the usage of the former attempt, should be called before every attempt,
+    /// so a retry is not billed with the usage of the failed one.
+    pub fn reset_usage(&mut self) {
+        self.usage = None;
+    }
+
+    /// Send a heartbeat to the stream client, e.g. while the request is queued.
+    /// This is not regarded as the first token.
+    pub async fn send_keep_alive(&self) -> Result<()> {
+        if !self.is_stream() {
+            return Ok(());
+        }
+
+        *self.last_activity.lock().await = Instant::now();
+        Ok(self.inner.send(Bytes::from(HEARTBEAT)).await?)
+    }
 }
 
 /// This trait defines the methods that are used to manage the channel buffer.
@@ -171,6 +227,7 @@ trait ChannelSenderUtil {
 
 impl ChannelSender for ClientSender {
     async fn send(&self, mut buffer: Vec<u8>) -> Result<()> {
+        self.first_send.get_or_init(Instant::now);
         {
             let mut last = self.last_activity.lock().await;
             *last = Instant::now();
@@ -270,7 +327,7 @@ impl ChannelSenderUtil for ClientSender {
                     "Success to get response, but serde json make an error: {:?}",
                     err
                 );
-                error!("{}", err);
+                error!("[{}] {}", self.request_id, err);
                 Ok(self.send(err.into_bytes()).await?)
             }
         }
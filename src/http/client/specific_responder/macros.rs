@@ -74,6 +74,17 @@ macro_rules! impl_specific_responder {
 
                 err.unwrap_or(back)
             }
+
+            async fn probe(&self,
+                           accessor: &AccountVisitor,
+                           model: &str,
+            ) -> Result<ProbeResult, ResponderError> {
+                match self {
+                    $(
+                        ResponderDispatcher::$responder(responder) => responder.probe(accessor, model).await,
+                    )*
+                }
+            }
         }
 
         impl Endpoint {
@@ -19,15 +19,30 @@ pub mod qianwen_responder;
 /// The error type for the responder module
 /// # Variants
 /// * `Request` - Error when try to send request to endpoint
+/// * `Status` - The endpoint responds with an error status, the body is kept to tell the client
 /// * `Response` - Error when try to response to client
 #[derive(Error, Debug)]
 pub(crate) enum ResponderError {
     #[error("Error when try to send request to endpoint: {0}")]
     Request(String),
+    #[error("Error when get response with code: {status}, error message: {body}")]
+    Status { status: u16, body: String },
     #[error("Error when try to response to client : {0}")]
     Response(String),
 }
 
+/// The result of probing an account.
+/// # Fields
+/// - status: The http status of the response.
+/// - models: The models available for the account, empty if the request is not success.
+/// - message: The error message of the endpoint if the request is not success.
+#[derive(Debug, Default)]
+pub struct ProbeResult {
+    pub status: u16,
+    pub models: Vec<String>,
+    pub message: Option<String>,
+}
+
 /// The trait that defines the method to make the response to the client.
 pub trait SpecificResponder {
     async fn make_response(
@@ -35,6 +50,16 @@ pub trait SpecificResponder {
         sender: &mut ClientSender,
         accessor: &AccountVisitor,
     ) -> Result<(), ResponderError>;
+
+    /// Send a minimal request to check if the account is usable, an error is only
+    /// returned when the endpoint can not be reached.
+    /// # Arguments
+    /// - model: A model of the endpoint in config, used if the endpoint can not list the models.
+    async fn probe(
+        &self,
+        accessor: &AccountVisitor,
+        model: &str,
+    ) -> Result<ProbeResult, ResponderError>;
 }
 
 /// The trait that defines the method to parse the response from the endpoint.
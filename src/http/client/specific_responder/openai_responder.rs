@@ -7,14 +7,19 @@ use crate::data::http_api::openai::openai_sync_response::OpenAISyncResponse;
 use crate::http::client::client_sender::channel_manager::{
     ChannelBufferManager, ChannelSender, ClientSender,
 };
-use crate::http::client::specific_responder::{ResponderError, ResponseParser, SpecificResponder};
+use crate::http::client::specific_responder::{ProbeResult, ResponderError, ResponseParser, SpecificResponder};
 
 #[derive(Default)]
 pub struct OpenAIResponder;
 
 /// The parser for the OpenAI response
+/// # Fields
+/// - forward_usage: Send the chunk that only contains the usage to the client, which is only
+///   expected if the client asks for it in `stream_options`.
 #[derive(Default)]
-struct OpenAIResponderParser;
+struct OpenAIResponderParser {
+    forward_usage: bool,
+}
 
 impl ResponseParser for OpenAIResponderParser {
     async fn parse_response(
@@ -33,6 +38,13 @@ impl ResponseParser for OpenAIResponderParser {
                 }
 
                 Ok(ok) => {
+                    if let Some(usage) = &ok.usage {
+                        sender.set_usage(usage.token_usage());
+                        if ok.choices.is_empty() && !self.forward_usage {
+                            return Ok(());
+                        }
+                    }
+
                     if let Some(choice) = ok.choices.first()
                         && let Some(content) = &choice.delta.content
                     {
@@ -68,6 +80,7 @@ impl ResponseParser for OpenAIResponderParser {
                 if let Some(choice) = response.choices.first() {
                     sender.append_buffer(choice.message.content.as_str());
                 }
+                sender.set_usage(response.usage.token_usage());
             }
         }
 
@@ -81,30 +94,86 @@ impl SpecificResponder for OpenAIResponder {
         sender: &mut ClientSender,
         accessor: &AccountVisitor,
     ) -> Result<(), ResponderError> {
+        let forward_usage = sender.request.stream_options.is_some();
+        let mut body = serde_json::to_value(&sender.request)
+            .map_err(|e| ResponderError::Request(e.to_string()))?;
+        // Ask for the usage of the stream, so that the cached and reasoning tokens can be billed.
+        if sender.is_stream() && accessor.stream_usage && !forward_usage {
+            body["stream_options"] = serde_json::json!({ "include_usage": true });
+        }
+
         let stream = accessor
             .client
-            .post(accessor.endpoint_url)
-            .body(
-                serde_json::to_string(&sender.request)
-                    .map_err(|e| ResponderError::Request(e.to_string()))?,
-            )
+            .post(accessor.endpoint_url.as_str())
+            .body(body.to_string())
             .send()
             .await
             .map_err(|e| ResponderError::Request(format!("Error when send request: {}, reason: {:?}", e, e.source())))?;
+        accessor.rate_limit.update(stream.headers());
 
         if stream.status() != StatusCode::OK {
-            return Err(ResponderError::Request(format!(
-                "Error when get response with code: {}, error message: {}",
-                stream.status(),
-                stream
+            return Err(ResponderError::Status {
+                status: stream.status().as_u16(),
+                body: stream
                     .text()
                     .await
-                    .map_err(|e| ResponderError::Request(e.to_string()))?
-            )));
+                    .map_err(|e| ResponderError::Request(e.to_string()))?,
+            });
         }
 
-        process_stream!(stream, OpenAIResponderParser::default(), sender);
+        process_stream!(stream, OpenAIResponderParser { forward_usage }, sender);
 
         Ok(())
     }
+
+    async fn probe(
+        &self,
+        accessor: &AccountVisitor,
+        _: &str,
+    ) -> Result<ProbeResult, ResponderError> {
+        let Some((base, _)) = accessor.endpoint_url.rsplit_once("/chat/completions") else {
+            return Err(ResponderError::Request(format!(
+                "Unable to find the model list url of {}",
+                accessor.endpoint_url
+            )));
+        };
+
+        let response = accessor
+            .client
+            .get(format!("{}/models", base))
+            .send()
+            .await
+            .map_err(|e| ResponderError::Request(format!("Error when send request: {}, reason: {:?}", e, e.source())))?;
+
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| ResponderError::Request(e.to_string()))?;
+
+        if status != StatusCode::OK {
+            return Ok(ProbeResult {
+                status: status.as_u16(),
+                models: vec![],
+                message: Some(body),
+            });
+        }
+
+        let models = serde_json::from_str::<serde_json::Value>(&body)
+            .map_err(|e| ResponderError::Request(format!("Error when parse model list: {}", e)))?
+            .get("data")
+            .and_then(|x| x.as_array())
+            .map(|x| {
+                x.iter()
+                    .filter_map(|model| model.get("id")?.as_str().map(|id| id.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(ProbeResult {
+            status: status.as_u16(),
+            models,
+            message: None,
+        })
+    }
 }
@@ -4,10 +4,11 @@ use reqwest::StatusCode;
 use crate::data::config::entity::runtime_data::AccountVisitor;
 use crate::data::http_api::alibaba::qian_wen_request::{Input, Parameters, QianWenRequest};
 use crate::data::http_api::alibaba::qian_wen_response::QianWenResponse;
+use crate::data::http_api::openai::openai_request::{Message, MessageContent};
 use crate::http::client::client_sender::channel_manager::{
     ChannelBufferManager, ChannelSender, ClientSender,
 };
-use crate::http::client::specific_responder::{ResponderError, ResponseParser, SpecificResponder};
+use crate::http::client::specific_responder::{ProbeResult, ResponderError, ResponseParser, SpecificResponder};
 
 /// The parser for the QianWen responder
 #[derive(Default)]
@@ -32,6 +33,7 @@ impl ResponseParser for QianWenResponderParser {
             }
 
             (Ok(response), false) => {
+                sender.set_usage(response.usage.token_usage());
                 if let Some(choice) = response.output.choices.first() {
                     let content = &choice.message.content;
                     sender.append_buffer(content.as_str());
@@ -39,6 +41,7 @@ impl ResponseParser for QianWenResponderParser {
             }
 
             (Ok(response), true) => {
+                sender.set_usage(response.usage.token_usage());
                 if let Some(choice) = response.output.choices.first() {
                     let content = &choice.message.content;
                     sender.append_buffer(content.as_str());
@@ -65,7 +68,7 @@ impl SpecificResponder for QianWenResponder {
     ) -> Result<(), ResponderError> {
         let stream = accessor
             .client
-            .post(accessor.endpoint_url)
+            .post(accessor.endpoint_url.as_str())
             .header(
                 "X-DashScope-SSE",
                 if sender.is_stream() {
@@ -82,6 +85,7 @@ impl SpecificResponder for QianWenResponder {
                 parameters: Parameters {
                     incremental_output: if sender.is_stream() { Some(true) } else { None },
                     result_format: "message".to_string(),
+                    max_tokens: None,
                 },
             })
             .send()
@@ -89,18 +93,61 @@ impl SpecificResponder for QianWenResponder {
             .map_err(|e| ResponderError::Request(format!("Error when send request: {}, reason: {:?}", e, e.source())))?;
 
         if stream.status() != StatusCode::OK {
-            return Err(ResponderError::Request(format!(
-                "Error when get response with code: {}, error message: {}",
-                stream.status(),
-                stream
+            return Err(ResponderError::Status {
+                status: stream.status().as_u16(),
+                body: stream
                     .text()
                     .await
-                    .map_err(|e| ResponderError::Request(e.to_string()))?
-            )));
+                    .map_err(|e| ResponderError::Request(e.to_string()))?,
+            });
         }
 
         process_stream!(stream, QianWenResponderParser::default(), sender);
 
         Ok(())
     }
+
+    async fn probe(
+        &self,
+        accessor: &AccountVisitor,
+        model: &str,
+    ) -> Result<ProbeResult, ResponderError> {
+        // QianWen can not list the models, so a generation with one token is sent.
+        let response = accessor
+            .client
+            .post(accessor.endpoint_url.as_str())
+            .header("X-DashScope-SSE", "disable")
+            .json(&QianWenRequest {
+                model: model.to_string(),
+                input: Input {
+                    messages: vec![Message {
+                        role: "user".to_string(),
+                        content: MessageContent::Common("ping".to_string()),
+                    }],
+                },
+                parameters: Parameters {
+                    incremental_output: None,
+                    result_format: "message".to_string(),
+                    max_tokens: Some(1),
+                },
+            })
+            .send()
+            .await
+            .map_err(|e| ResponderError::Request(format!("Error when send request: {}, reason: {:?}", e, e.source())))?;
+
+        let status = response.status();
+        if status != StatusCode::OK {
+            return Ok(ProbeResult {
+                status: status.as_u16(),
+                models: vec![],
+                message: Some(response.text().await.map_err(|e| ResponderError::Request(e.to_string()))?),
+            });
+        }
+
+        Ok(ProbeResult {
+            status: status.as_u16(),
+            models: vec![model.to_string()],
+            message: None,
+        })
+    }
 }
@@ -1,39 +1,236 @@
-use anyhow::Result;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use log::{error, info};
 use rayon::prelude::*;
-use sqlx::Pool;
+use sqlx::{Pool, Transaction};
 use sqlx_postgres::Postgres;
 
 use crate::data::config::entity::config_file::Config;
 use crate::data::config::entity::endpoint::Endpoint;
-use crate::data::config::entity::runtime_data::AccountVisitor;
+use crate::data::config::entity::runtime_data::{AccountPool, AccountVisitor, GlobalData};
+use crate::data::database::account_secret::{encrypt_plaintext_accounts, lock_account_keys, AccountSecret, MasterKey};
 use crate::data::database::entity::data_base_account::DataBaseAccount;
+use crate::http::client::util::account_stats::AccountStats;
+use crate::http::client::util::counter::concurrency_pool::{SafePool, VecSafePool};
 use crate::http::client::util::get_reqwest_client::get_client;
+use crate::http::client::util::rate_limit::RateLimitState;
 
+/// Load the active accounts from database, the api keys still in plain text will be
+/// encrypted before loading, the accounts over their monthly budget are not loaded.
 pub async fn load_account_from_database(
     config: &Config,
     db: &Pool<Postgres>,
+    master_key: &MasterKey,
 ) -> Result<Vec<AccountVisitor>> {
+    encrypt_plaintext_accounts(db, master_key).await?;
+
     let row: Vec<DataBaseAccount> = sqlx::query_as!(
         DataBaseAccount,
-        "SELECT * from account_list WHERE is_disabled = FALSE"
+        "SELECT * from account_list WHERE is_disabled = FALSE AND budget_exceeded_month IS NULL"
     ).fetch_all(db).await?;
 
-    let back = row
+    row
         .into_par_iter()
-        .map(|account| {
-            let endpoint = Endpoint::from_str(account.endpoint.leak(), config).unwrap();
-            let client = get_client(&account.use_proxy, &config, &endpoint, &account.api_key);
-            AccountVisitor {
-                account_id: account.id,
-                endpoint_url: endpoint.to_url(config).expect("Failed to get endpoint url").leak(),
-
-                responder: endpoint.specific_responder_dispatcher(),
-
-                endpoint,
-                client,
-            }
-        })
-        .collect::<Vec<AccountVisitor>>();
-
-    Ok(back)
+        .map(|account| to_account_visitor(account, config, master_key))
+        .collect::<Result<Vec<AccountVisitor>>>()
+}
+
+/// Build the visitor of an account, an error will be returned if the endpoint
+/// or the proxy of the account is not found in config, or the key can not be decrypted.
+pub fn to_account_visitor(
+    account: DataBaseAccount,
+    config: &Config,
+    master_key: &MasterKey,
+) -> Result<AccountVisitor> {
+    let settings = account.settings();
+    let endpoint = Endpoint::from_str(&account.endpoint, config)
+        .map_err(|e| anyhow!("Account {}: {}", account.id, e))?;
+
+    let (Some(encrypted_key), Some(data_key)) = (account.encrypted_key, account.data_key) else {
+        return Err(anyhow!("Account {}: api key is not encrypted", account.id));
+    };
+    let secret = AccountSecret { encrypted_key, data_key };
+
+    let client = get_client(&account.use_proxy, config, &endpoint, &secret, master_key)
+        .map_err(|e| anyhow!("Account {}: {}", account.id, e))?;
+
+    Ok(AccountVisitor {
+        account_id: account.id,
+        endpoint_url: endpoint.to_url(config)?,
+
+        responder: endpoint.specific_responder_dispatcher(),
+
+        endpoint,
+        client,
+        rate_limit: RateLimitState::default(),
+        stream_usage: config.stream_usage,
+        stats: AccountStats::default(),
+        settings,
+        max_concurrency: account
+            .max_concurrency
+            .map_or(config.request_concurrency_count, |x| x.max(1) as u32),
+    })
+}
+
+/// Sync the whole account pool with database, returns the number of accounts in the new pool.
+/// The pools of the unchanged accounts are kept, so their slots and stats are not reset.
+pub async fn sync_account_pool(global_data: &GlobalData) -> Result<usize> {
+    let config = global_data.config.read().clone();
+    let master_key = global_data.master_key.load_full();
+    let accounts = load_account_from_database(&config, &global_data.data_base, &master_key).await?;
+
+    let current = global_data.account_pool.load_full();
+    let mut pool = AccountPool::new();
+    let mut changed = Vec::new();
+    for visitor in accounts {
+        match current.iter().find(|x| is_unchanged(x, &visitor)) {
+            Some(kept) => pool.push(kept.clone()),
+            None => changed.push(visitor),
+        }
+    }
+    pool.extend(changed.to_vec_safe_pool(&config));
+    let count = pool.len();
+    global_data.account_pool.store(Arc::new(pool));
+
+    Ok(count)
+}
+
+/// Check if the pool is built from the same settings as the visitor.
+fn is_unchanged(pool: &SafePool<AccountVisitor>, visitor: &AccountVisitor) -> bool {
+    pool.get_account_id() == visitor.account_id && pool.get_visitor().settings == visitor.settings
+}
+
+/// Refresh a single account in the pool, which is called by the notification when the account
+/// is changed in database by this instance or another one.
+/// The account is removed from the pool if it is disabled, deleted or over its monthly budget, otherwise it is
+/// rebuilt and put into the pool if its settings are changed, the other accounts are not touched.
+pub async fn refresh_account(global_data: &GlobalData, account_id: i32) -> Result<()> {
+    let account = sqlx::query_as!(
+        DataBaseAccount,
+        "SELECT * from account_list WHERE id = $1",
+        account_id
+    ).fetch_optional(&global_data.data_base).await?;
+
+    let Some(account) = account.filter(|x| !x.is_disabled && x.budget_exceeded_month.is_none()) else {
+        if !global_data.account_pool.load().iter().any(|x| x.get_account_id() == account_id) {
+            return Ok(());
+        }
+
+        global_data.account_pool.rcu(|pool| {
+            pool.iter().filter(|x| x.get_account_id() != account_id).cloned().collect::<AccountPool>()
+        });
+        info!("Account {} has been removed from the pool.", account_id);
+        return Ok(());
+    };
+
+    if account.api_key.is_some() {
+        // The account will be refreshed again by the notification of the encryption.
+        let master_key = global_data.master_key.load_full();
+        return encrypt_plaintext_accounts(&global_data.data_base, &master_key).await;
+    }
+
+    let settings = account.settings();
+    if global_data
+        .account_pool
+        .load()
+        .iter()
+        .any(|x| x.get_account_id() == account_id && x.get_visitor().settings == settings)
+    {
+        info!("Account {} is not changed, the pool is kept.", account_id);
+        return Ok(());
+    }
+
+    let safe_pool = {
+        let config = global_data.config.read();
+        let visitor = to_account_visitor(account, &config, &global_data.master_key.load())?;
+        let concurrency_count = visitor.max_concurrency;
+        Arc::new(SafePool::new(
+            visitor,
+            concurrency_count,
+            config.reserved_concurrency,
+            &config.model_concurrency.clone().unwrap_or_default(),
+        ))
+    };
+
+    global_data.account_pool.rcu(|pool| {
+        let mut pool = pool.iter().filter(|x| x.get_account_id() != account_id).cloned().collect::<AccountPool>();
+        pool.push(safe_pool.clone());
+        pool
+    });
+    info!(
+        "Account {} has been refreshed, now {} accounts in pool.",
+        account_id,
+        global_data.account_pool.load().len()
+    );
+
+    Ok(())
+}
+
+/// Validate the account and insert it with the encrypted key, returns the id of the account.
+/// The pool is not changed here, the account is put into the pool by the notification of the insert.
+/// The table of the accounts is locked until the transaction ends, see `lock_account_keys`.
+/// # Arguments
+/// - endpoint: The endpoint of the account, or an alias in `endpoint_mapping`.
+/// - proxy: The name of the proxy in config.
+pub async fn insert_account(
+    transaction: &mut Transaction<'_, Postgres>,
+    config: &Config,
+    master_key: &MasterKey,
+    endpoint: &str,
+    api_key: &str,
+    proxy: Option<&str>,
+) -> Result<i32> {
+    Endpoint::from_str(endpoint, config)?;
+
+    if let Some(proxy) = proxy
+        && !config.proxy.as_ref().is_some_and(|x| x.contains_key(proxy))
+    {
+        return Err(anyhow!("Proxy server {} is not set.", proxy));
+    }
+
+    lock_account_keys(transaction, master_key).await?;
+
+    let secret = master_key.encrypt(api_key)?;
+    let id = sqlx::query_scalar!(
+        r#"INSERT INTO account_list (use_proxy, endpoint, encrypted_key, data_key)
+        VALUES ($1, $2, $3, $4) RETURNING id"#,
+        proxy,
+        endpoint,
+        secret.encrypted_key,
+        secret.data_key
+    ).fetch_one(&mut **transaction).await?;
+
+    Ok(id)
+}
+
+/// Put the accounts over budget in the last months back, returns the ids of them.
+/// The pools are refreshed by the notifications of the update.
+pub async fn reset_account_budgets(db: &Pool<Postgres>) -> Result<Vec<i32>> {
+    let ids = sqlx::query_scalar!(
+        r#"UPDATE account_list SET budget_exceeded_month = NULL
+        WHERE budget_exceeded_month < date_trunc('month', LOCALTIMESTAMP)::date RETURNING id"#
+    ).fetch_all(db).await?;
+
+    Ok(ids)
+}
+
+/// Reset the budgets of the accounts at the start of every month until the server is stopped.
+pub async fn schedule_budget_reset(global_data: &'static GlobalData) {
+    loop {
+        match reset_account_budgets(&global_data.data_base).await {
+            Ok(ids) if !ids.is_empty() => info!("The monthly budget of accounts {:?} has been reset.", ids),
+            Ok(_) => {}
+            Err(e) => error!("Error when reset the monthly budget of accounts: {}", e),
+        }
+
+        let until_next_month = sqlx::query_scalar!(
+            r#"SELECT EXTRACT(EPOCH FROM date_trunc('month', LOCALTIMESTAMP) + INTERVAL '1 month' - LOCALTIMESTAMP)::float8 AS "seconds!""#
+        ).fetch_one(&global_data.data_base).await;
+
+        // Retry in an hour if the database is not reachable.
+        let wait = until_next_month.map_or(3600.0, |x| x.max(0.0) + 1.0);
+        tokio::time::sleep(Duration::from_secs_f64(wait)).await;
+    }
 }
@@ -0,0 +1,89 @@
+use std::time::Duration;
+
+use parking_lot::Mutex;
+
+/// The weight of the latest attempt in the moving averages.
+const ALPHA: f64 = 0.2;
+
+/// The slowest account still gets this ratio of the weight of the fastest one, so that it
+/// keeps being measured and can be favored again after it recovers.
+const EXPLORE_RATIO: f64 = 0.1;
+
+/// The account is regarded as unhealthy when the error rate is above this.
+const UNHEALTHY_ERROR_RATE: f64 = 0.5;
+
+/// The time to first token below this is regarded as equal, so a few fast responses do not
+/// make an account take all the traffic.
+const MIN_TTFT: f64 = 0.05;
+
+/// The performance of an account observed from the recent attempts.
+/// # Fields
+/// - ttft: The exponentially weighted moving average of the time to first token in seconds,
+///   `None` if no stream response is observed yet.
+/// - error_rate: The exponentially weighted moving average of the failed attempts.
+/// - attempts: The number of the observed attempts.
+#[derive(Debug, Default, Clone)]
+pub struct AccountStatsInfo {
+    pub ttft: Option<f64>,
+    pub error_rate: f64,
+    pub attempts: u64,
+}
+
+/// The performance stats of an account, which is updated after each attempt.
+#[derive(Debug, Default)]
+pub struct AccountStats {
+    inner: Mutex<AccountStatsInfo>,
+}
+
+impl AccountStats {
+    /// Record an attempt on the account.
+    /// # Arguments
+    /// - ttft: The time to first token of the attempt, only the stream responses have it.
+    /// - failed: If the attempt failed because of the endpoint or the account.
+    pub fn record(&self, ttft: Option<Duration>, failed: bool) {
+        let mut info = self.inner.lock();
+
+        if let Some(ttft) = ttft {
+            let ttft = ttft.as_secs_f64();
+            info.ttft = Some(info.ttft.map_or(ttft, |x| ALPHA * ttft + (1.0 - ALPHA) * x));
+        }
+
+        let error = if failed { 1.0 } else { 0.0 };
+        info.error_rate = match info.attempts {
+            0 => error,
+            _ => ALPHA * error + (1.0 - ALPHA) * info.error_rate,
+        };
+        info.attempts += 1;
+    }
+
+    pub fn info(&self) -> AccountStatsInfo {
+        self.inner.lock().clone()
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.inner.lock().error_rate <= UNHEALTHY_ERROR_RATE
+    }
+
+    /// The relative speed of the account, `None` if it is not measured yet.
+    pub fn speed(&self) -> Option<f64> {
+        let info = self.inner.lock();
+        let ttft = info.ttft?;
+
+        Some((1.0 - info.error_rate).max(0.0) / ttft.max(MIN_TTFT))
+    }
+}
+
+/// Turn the speeds of the accounts into the weights of the selection.
+/// The accounts not measured yet get the max weight so that they are measured soon, and
+/// the others get at least `EXPLORE_RATIO` of the max weight.
+pub fn selection_weights(speeds: &[Option<f64>]) -> Vec<f64> {
+    let max = speeds.iter().flatten().copied().fold(0.0, f64::max);
+    if max <= 0.0 {
+        return vec![1.0; speeds.len()];
+    }
+
+    speeds
+        .iter()
+        .map(|x| x.map_or(max, |x| x.max(max * EXPLORE_RATIO)))
+        .collect()
+}
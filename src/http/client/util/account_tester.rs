@@ -0,0 +1,149 @@
+use std::time::{Duration, Instant};
+
+use futures_util::StreamExt;
+use log::{error, info, warn};
+use serde::Serialize;
+
+use crate::data::config::entity::runtime_data::{AccountVisitor, GlobalData};
+use crate::http::client::specific_responder::SpecificResponder;
+
+/// The number of accounts tested at the same time.
+const TEST_CONCURRENCY: usize = 8;
+
+/// The result of testing an account.
+/// # Fields
+/// - account_id: The id of the account.
+/// - endpoint: The endpoint of the account.
+/// - latency_ms: The time of the probe in milliseconds.
+/// - status: The http status of the endpoint, `None` if the endpoint can not be reached.
+/// - models: The models available for the account.
+/// - error: The error message if the account is not usable.
+#[derive(Debug, Serialize)]
+pub struct AccountTestResult {
+    pub account_id: i32,
+    pub endpoint: String,
+    pub latency_ms: u128,
+    pub status: Option<u16>,
+    pub models: Vec<String>,
+    pub error: Option<String>,
+}
+
+impl AccountTestResult {
+    pub fn is_ok(&self) -> bool {
+        self.status.is_some_and(|x| (200..300).contains(&x))
+    }
+
+    /// The key is rejected by the endpoint, e.g. invalid or revoked.
+    /// Rate limits, server errors and network errors are not regarded as dead, neither is `403`,
+    /// which a region block or a misconfigured proxy returns for every key.
+    pub fn is_dead(&self) -> bool {
+        self.status == Some(401)
+    }
+}
+
+/// Send a minimal request through the account, and report the latency, status and models.
+pub async fn test_account(global_data: &GlobalData, account: &AccountVisitor) -> AccountTestResult {
+    let model = global_data
+        .model_info
+        .read()
+        .any_model(&account.endpoint)
+        .unwrap_or_default()
+        .to_string();
+
+    let start = Instant::now();
+    let result = account.responder.probe(account, &model).await;
+    let latency_ms = start.elapsed().as_millis();
+
+    match result {
+        Ok(probe) => AccountTestResult {
+            account_id: account.account_id,
+            endpoint: account.endpoint.to_string(),
+            latency_ms,
+            status: Some(probe.status),
+            models: probe.models,
+            error: probe.message,
+        },
+        Err(e) => AccountTestResult {
+            account_id: account.account_id,
+            endpoint: account.endpoint.to_string(),
+            latency_ms,
+            status: None,
+            models: vec![],
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+/// Test the accounts in the pool, or only the one with the id.
+pub async fn test_accounts(global_data: &GlobalData, account_id: Option<i32>) -> Vec<AccountTestResult> {
+    // The futures own the pools, borrowing them from the snapshot makes the spawned callers not `Send`.
+    let accounts = global_data
+        .account_pool
+        .load()
+        .iter()
+        .filter(|x| account_id.is_none_or(|id| x.get_account_id() == id))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let mut results = futures::stream::iter(accounts)
+        .map(|x| async move { test_account(global_data, x.get_visitor()).await })
+        .buffer_unordered(TEST_CONCURRENCY)
+        .collect::<Vec<_>>()
+        .await;
+
+    results.sort_by_key(|x| x.account_id);
+    results
+}
+
+/// Disable the dead accounts in the results, returns the ids of them.
+pub async fn disable_dead_accounts(
+    global_data: &GlobalData,
+    results: &[AccountTestResult],
+) -> anyhow::Result<Vec<i32>> {
+    let dead = results
+        .iter()
+        .filter(|x| x.is_dead())
+        .map(|x| x.account_id)
+        .collect::<Vec<_>>();
+
+    for account_id in dead.iter() {
+        sqlx::query!(
+            r#"UPDATE account_list SET is_disabled = TRUE WHERE id = $1"#,
+            account_id
+        )
+            .execute(&global_data.data_base)
+            .await?;
+
+        // The account is taken out of the pool by the notification of the update.
+        warn!("Account {} is rejected by the endpoint and has been disabled.", account_id);
+    }
+
+    Ok(dead)
+}
+
+/// Test all the accounts every `account_test_interval` seconds and disable the dead ones.
+/// Nothing is tested when the interval is `0`, the interval is read again after each round
+/// so it can be changed by a config reload.
+pub async fn schedule_account_test(global_data: &'static GlobalData) {
+    loop {
+        let interval = global_data.config.read().account_test_interval;
+        if interval == 0 {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            continue;
+        }
+
+        tokio::time::sleep(Duration::from_secs(interval)).await;
+
+        let results = test_accounts(global_data, None).await;
+        let failed = results.iter().filter(|x| !x.is_ok()).count();
+        match disable_dead_accounts(global_data, &results).await {
+            Ok(dead) => info!(
+                "Scheduled account test finished, {} accounts tested, {} failed, {} disabled.",
+                results.len(),
+                failed,
+                dead.len()
+            ),
+            Err(e) => error!("Error when disable dead accounts: {}", e),
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! The affinity between the conversations and the accounts.
+//! The endpoints discount the prompt prefixes cached on the same key, so the later turns of a
+//! conversation prefer the account that served the previous turn.
+
+use std::time::{Duration, Instant};
+
+use hashbrown::HashMap;
+use parking_lot::RwLock;
+use sha2::{Digest, Sha256};
+
+use crate::data::config::entity::runtime_data::GlobalData;
+use crate::data::http_api::openai::openai_request::Message;
+
+/// The interval of evicting the expired entries.
+const EVICT_INTERVAL: Duration = Duration::from_secs(60);
+
+struct AffinityEntry {
+    account_id: i32,
+    used_at: Instant,
+}
+
+/// The map of conversation → account, the conversations are stored as their SHA-256 hash.
+#[derive(Default)]
+pub struct AffinityMap {
+    entries: RwLock<HashMap<String, AffinityEntry>>,
+}
+
+impl AffinityMap {
+    /// Get the account that served the conversation, `None` if it is older than the ttl.
+    pub fn get(&self, key: &str, ttl: Duration) -> Option<i32> {
+        let entries = self.entries.read();
+        let entry = entries.get(key)?;
+
+        if entry.used_at.elapsed() >= ttl {
+            return None;
+        }
+
+        Some(entry.account_id)
+    }
+
+    pub fn insert(&self, key: String, account_id: i32) {
+        let mut entries = self.entries.write();
+        entries.insert(key, AffinityEntry { account_id, used_at: Instant::now() });
+    }
+
+    /// Remove the entries older than the ttl.
+    pub fn evict_expired(&self, ttl: Duration) {
+        self.entries.write().retain(|_, x| x.used_at.elapsed() < ttl);
+    }
+}
+
+/// Evict the expired entries every minute until the server is stopped.
+pub async fn schedule_affinity_eviction(global_data: &'static GlobalData) {
+    let mut interval = tokio::time::interval(EVICT_INTERVAL);
+    loop {
+        interval.tick().await;
+        let ttl = Duration::from_secs(global_data.config.read().affinity_ttl);
+        global_data.affinity.evict_expired(ttl);
+    }
+}
+
+/// The key of the conversation, which is the hash of the user and the messages up to the
+/// first user input, e.g. the system prompt and the first question, which are kept in the
+/// later turns.
+pub fn conversation_key(user_id: i32, messages: &[Message]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(user_id.to_be_bytes());
+
+    let first_input = messages
+        .iter()
+        .position(|x| x.role == "user")
+        .map_or(messages.len(), |x| x + 1);
+    for message in messages[..first_input].iter() {
+        hasher.update(serde_json::to_vec(message).unwrap_or_default());
+    }
+
+    format!("{:x}", hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::http_api::openai::openai_request::MessageContent;
+
+    fn message(role: &str, content: &str) -> Message {
+        Message {
+            role: role.to_string(),
+            content: MessageContent::Common(content.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_conversation_key_of_later_turns() {
+        let first = [message("user", "hi")];
+        let second = [message("user", "hi"), message("assistant", "hello"), message("user", "how are you")];
+        assert_eq!(conversation_key(1, &first), conversation_key(1, &second));
+        assert_ne!(conversation_key(1, &first), conversation_key(2, &first));
+
+        let first = [message("system", "be brief"), message("user", "hi")];
+        let second = [message("system", "be brief"), message("user", "hi"), message("assistant", "hello")];
+        assert_eq!(conversation_key(1, &first), conversation_key(1, &second));
+        assert_ne!(conversation_key(1, &first), conversation_key(1, &[message("user", "hi")]));
+    }
+}
@@ -2,29 +2,37 @@ use std::ops::Deref;
 use std::sync::Arc;
 use std::time::Duration;
 
+use futures::future::select_all;
+use hashbrown::HashMap;
 use rand::rngs::OsRng;
 use rand::TryRngCore;
-use rayon::prelude::*;
-use tokio::sync::mpsc::Sender;
-use tokio::time::sleep;
+use tokio::sync::{Semaphore, SemaphorePermit};
 
+use crate::data::config::entity::config_file::Config;
 use crate::data::config::entity::endpoint::Endpoint;
 use crate::data::config::entity::runtime_data::AccountVisitor;
-use crate::http::client::util::counter::counter::Counter;
+use crate::http::client::util::account_stats::selection_weights;
 
 /// The safe pool that use to manage the concurrency, we can promise that the concurrency
-/// will in the range of the concurrency_count
+/// will in the range of the concurrency_count, and the concurrency of each model will
+/// in the range of its limit.
+/// The low priority requests also take a slot of the shared semaphore, so that some slots
+/// are always left for the high priority requests.
 pub struct SafePool<T> {
-    counter: Arc<Vec<Counter>>,
-    sender: Sender<()>,
+    concurrency_count: u32,
+    semaphore: Semaphore,
+    shared_semaphore: Semaphore,
+    model_semaphore: HashMap<String, Semaphore>,
     inner: T,
 }
 
 /// The safe object that use to lock the concurrency
-/// When the object is dropped, the concurrency will be unlocked
+/// When the object is dropped, the slots are released and the waiters are woken up.
 pub struct SafeObject<'a, T> {
     inner: T,
-    counter: &'a Counter,
+    _permit: SemaphorePermit<'a>,
+    _shared_permit: Option<SemaphorePermit<'a>>,
+    _model_permit: Option<SemaphorePermit<'a>>,
 }
 
 impl<T> Deref for SafeObject<'_, T> {
@@ -35,125 +43,230 @@ impl<T> Deref for SafeObject<'_, T> {
     }
 }
 
-impl<T> Drop for SafeObject<'_, T> {
-    fn drop(&mut self) {
-        self.counter.unlock();
-    }
-}
-
 impl<T> SafePool<T> {
-    pub fn new(inner: T, concurrency_count: u32) -> Self {
-        let mut counter = Vec::new();
-        for _ in 0..concurrency_count {
-            counter.push(Counter::default());
-        }
-        let counter = Arc::new(counter);
-
-        let sender = {
-            let counter = counter.clone();
-            let (sender, mut receiver) =
-                tokio::sync::mpsc::channel::<()>(concurrency_count as usize);
-
-            tokio::spawn(async move {
-                let mut interval = tokio::time::interval(Duration::from_secs(1));
-
-                loop {
-                    interval.tick().await;
-
-                    let tmp = counter
-                        .par_iter()
-                        .filter(|&counter| !counter.is_active())
-                        .map(|counter| {
-                            if counter.next_tick() {
-                                counter.tick();
-                            }
-                        })
-                        .count() as u32;
-
-                    if tmp == 0 {
-                        receiver.recv().await;
-                        interval = tokio::time::interval(Duration::from_secs(1));
-                    } else {
-                        loop {
-                            if let Err(_) = receiver.try_recv() {
-                                break;
-                            }
-                        }
-                    }
-                }
-            });
-
-            sender
-        };
+    /// Create the pool of the object.
+    /// # Arguments
+    /// - concurrency_count: The max concurrent requests of the object.
+    /// - reserved_concurrency: The slots that only the high priority requests can use,
+    ///   at least one slot is left for the low priority requests.
+    /// - model_concurrency: The max concurrent requests of each model on the object,
+    ///   the models not in it are only limited by the concurrency_count.
+    pub fn new(
+        inner: T,
+        concurrency_count: u32,
+        reserved_concurrency: u32,
+        model_concurrency: &HashMap<String, u32>,
+    ) -> Self {
+        let shared_count = concurrency_count.saturating_sub(reserved_concurrency).max(1);
 
         SafePool {
-            counter,
-            sender,
+            concurrency_count,
+            semaphore: Semaphore::new(concurrency_count as usize),
+            shared_semaphore: Semaphore::new(shared_count as usize),
+            model_semaphore: model_concurrency
+                .iter()
+                .map(|(model, count)| (model.clone(), Semaphore::new(*count as usize)))
+                .collect(),
             inner,
         }
     }
+
+    /// The number of slots that are currently in use.
+    pub fn in_flight(&self) -> usize {
+        self.concurrency_count as usize - self.semaphore.available_permits()
+    }
+
+    fn try_acquire(&self, model: &str, high_priority: bool) -> Option<SafeObject<'_, &T>> {
+        let shared_permit = match high_priority {
+            true => None,
+            false => Some(self.shared_semaphore.try_acquire().ok()?),
+        };
+        let model_permit = match self.model_semaphore.get(model) {
+            Some(semaphore) => Some(semaphore.try_acquire().ok()?),
+            None => None,
+        };
+
+        Some(SafeObject {
+            inner: &self.inner,
+            _permit: self.semaphore.try_acquire().ok()?,
+            _shared_permit: shared_permit,
+            _model_permit: model_permit,
+        })
+    }
+
+    async fn acquire(&self, model: &str, high_priority: bool) -> Option<SafeObject<'_, &T>> {
+        let shared_permit = match high_priority {
+            true => None,
+            false => Some(self.shared_semaphore.acquire().await.ok()?),
+        };
+        let model_permit = match self.model_semaphore.get(model) {
+            Some(semaphore) => Some(semaphore.acquire().await.ok()?),
+            None => None,
+        };
+
+        Some(SafeObject {
+            inner: &self.inner,
+            _permit: self.semaphore.acquire().await.ok()?,
+            _shared_permit: shared_permit,
+            _model_permit: model_permit,
+        })
+    }
 }
 
 pub trait VecSafePool {
     type Inner;
 
-    fn to_vec_safe_pool(self, concurrency_count: u32) -> Vec<SafePool<Self::Inner>>;
+    fn to_vec_safe_pool(self, config: &Config) -> Vec<Arc<SafePool<Self::Inner>>>;
 }
 
-impl<T> VecSafePool for Vec<T> {
-    type Inner = T;
+impl VecSafePool for Vec<AccountVisitor> {
+    type Inner = AccountVisitor;
 
-    fn to_vec_safe_pool(self, concurrency_count: u32) -> Vec<SafePool<Self::Inner>> {
-        let mut result = Vec::new();
-        for x in self {
-            result.push(SafePool::new(x, concurrency_count));
-        }
-        result
+    fn to_vec_safe_pool(self, config: &Config) -> Vec<Arc<SafePool<Self::Inner>>> {
+        let model_concurrency = config.model_concurrency.clone().unwrap_or_default();
+
+        self.into_iter()
+            .map(|x| {
+                let concurrency_count = x.max_concurrency;
+                Arc::new(SafePool::new(x, concurrency_count, config.reserved_concurrency, &model_concurrency))
+            })
+            .collect()
     }
 }
 
+/// The object in the pool that can ask to be skipped for a while, e.g. an account that
+/// is nearly rate limited by the endpoint.
+pub trait Throttled {
+    fn is_throttled(&self) -> bool;
+}
+
+/// The object in the pool that reports how fast it responds, the faster ones are preferred.
+pub trait Measured {
+    /// The relative speed of the object, `None` if it is not measured yet.
+    fn speed(&self) -> Option<f64>;
+}
+
 pub trait VecGettable {
     type Output;
 
-    async fn get_safe_object(&self) -> Option<SafeObject<&Self::Output>>;
+    /// Get a free object for the model, the objects rejected by the filter are never used.
+    /// Wait until a slot is released if all of them are busy, `None` if no slot is released
+    /// in `max_wait` or no object can be used. Only the free objects are tried if `max_wait` is zero.
+    /// The reserved slots of the objects are only used by the `high_priority` requests.
+    async fn get_safe_object(
+        &self,
+        model: &str,
+        filter: impl Fn(&Self::Output) -> bool,
+        max_wait: Duration,
+        high_priority: bool,
+    ) -> Option<SafeObject<'_, &Self::Output>>;
+
+    /// Get the free object accepted by the filter without waiting, e.g. the account that served
+    /// the previous turn of the conversation, `None` if it is busy or throttled.
+    fn get_preferred_object(
+        &self,
+        model: &str,
+        filter: impl Fn(&Self::Output) -> bool,
+        high_priority: bool,
+    ) -> Option<SafeObject<'_, &Self::Output>>;
 }
 
-impl<T> VecGettable for Vec<SafePool<T>> {
+impl<T: Throttled + Measured> VecGettable for Vec<Arc<SafePool<T>>> {
     type Output = T;
 
-    async fn get_safe_object(&self) -> Option<SafeObject<&Self::Output>> {
+    async fn get_safe_object(
+        &self,
+        model: &str,
+        filter: impl Fn(&Self::Output) -> bool,
+        max_wait: Duration,
+        high_priority: bool,
+    ) -> Option<SafeObject<'_, &Self::Output>> {
+        let candidates = self.iter().filter(|x| filter(&x.inner)).collect::<Vec<_>>();
+
+        // The throttled objects are skipped, unless all of them are throttled.
+        let available = candidates
+            .iter()
+            .copied()
+            .filter(|x| !x.inner.is_throttled())
+            .collect::<Vec<_>>();
+        let candidates = if available.is_empty() { candidates } else { available };
+
+        if candidates.is_empty() {
+            return None;
+        }
+
         //添加偏置条件，防止在并发情况下，每次都是第一个对象被选中
-        let preference = if self.len() == 1 {
+        //偏置按照账户的响应速度加权，响应更快的账户更容易被优先选中
+        let preference = if candidates.len() == 1 {
             0
         } else {
-            OsRng.try_next_u64().ok().map(|x| x as usize % self.len()).unwrap_or(0)
+            let speeds = candidates.iter().map(|x| x.inner.speed()).collect::<Vec<_>>();
+            weighted_index(&selection_weights(&speeds))
         };
 
-        for _ in 0..30 {
-            let safe_pool = self.get(preference)?;
-            for counter in safe_pool.counter.iter() {
-                if !counter.is_active() {
-                    continue;
-                }
+        let object = candidates
+            .iter()
+            .cycle()
+            .skip(preference)
+            .take(candidates.len())
+            .find_map(|x| x.try_acquire(model, high_priority));
+        if object.is_some() || max_wait.is_zero() {
+            return object;
+        }
 
-                counter.lock();
-                safe_pool.sender.send(()).await.unwrap();
+        // All the candidates are busy, wait for the first one that releases a slot,
+        // the other waiters are cancelled when it is dropped.
+        let waiters = candidates
+            .iter()
+            .map(|x| Box::pin(x.acquire(model, high_priority)))
+            .collect::<Vec<_>>();
 
-                return Some(SafeObject {
-                    inner: &safe_pool.inner,
-                    counter,
-                });
-            }
+        let (object, _, _) = tokio::time::timeout(max_wait, select_all(waiters))
+            .await
+            .ok()?;
+        object
+    }
 
-            sleep(Duration::from_secs(1)).await;
-        }
+    fn get_preferred_object(
+        &self,
+        model: &str,
+        filter: impl Fn(&Self::Output) -> bool,
+        high_priority: bool,
+    ) -> Option<SafeObject<'_, &Self::Output>> {
+        self.iter()
+            .filter(|x| filter(&x.inner) && !x.inner.is_throttled())
+            .find_map(|x| x.try_acquire(model, high_priority))
+    }
+}
+
+/// Pick an index randomly, the chance of each index is proportional to its weight.
+fn weighted_index(weights: &[f64]) -> usize {
+    let total = weights.iter().sum::<f64>();
+    let Ok(random) = OsRng.try_next_u64() else {
+        return 0;
+    };
 
-        None
+    let mut point = (random >> 11) as f64 / (1u64 << 53) as f64 * total;
+    for (index, weight) in weights.iter().enumerate() {
+        if point < *weight {
+            return index;
+        }
+        point -= weight;
     }
+
+    weights.len() - 1
 }
 
 impl SafePool<AccountVisitor> {
     pub fn get_endpoint(&self) -> &Endpoint {
         &self.inner.endpoint
     }
+
+    pub fn get_account_id(&self) -> i32 {
+        self.inner.account_id
+    }
+
+    pub fn get_visitor(&self) -> &AccountVisitor {
+        &self.inner
+    }
 }
@@ -1,6 +1 @@
-use std::ops::Range;
-
-const SLEEP_RANGE: Range<u8> = 7_u8..10_u8;
-
 pub mod concurrency_pool;
-mod counter;
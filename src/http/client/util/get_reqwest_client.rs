@@ -1,18 +1,25 @@
 use std::time::Duration;
 
+use anyhow::anyhow;
 use log::info;
 use reqwest::header::{HeaderMap, HeaderValue};
 use reqwest::{Client, Proxy};
 
 use crate::data::config::entity::config_file::Config;
 use crate::data::config::entity::endpoint::Endpoint;
+use crate::data::database::account_secret::{AccountSecret, MasterKey};
 
 pub fn get_client(
     proxy_config: &Option<String>,
     config: &Config,
     endpoint: &Endpoint,
-    token: &str,
-) -> Client {
+    secret: &AccountSecret,
+    master_key: &MasterKey,
+) -> anyhow::Result<Client> {
+    // The api key is only decrypted here, and it is kept in the default headers of the client.
+    let token = master_key.decrypt(secret)?;
+    let token = token.as_str();
+
     let client = Client::builder()
         .read_timeout(Duration::from_secs(config.request_timeout))
         .default_headers(match endpoint {
@@ -28,9 +35,9 @@ pub fn get_client(
     {
         let proxy = config.proxy
             .as_ref()
-            .expect("Proxy config is not set.")
+            .ok_or_else(|| anyhow!("Proxy config is not set."))?
             .get(proxy_server_name)
-            .expect(format!("Proxy server {} is not set.", proxy_server_name).as_str());
+            .ok_or_else(|| anyhow!("Proxy server {} is not set.", proxy_server_name))?;
 
         let address = format!(
             "{}://{}:{}@{}",
@@ -38,12 +45,12 @@ pub fn get_client(
         );
         info!("Create client with proxy: {}", proxy_server_name);
 
-        client.proxy(Proxy::all(address).unwrap())
+        client.proxy(Proxy::all(address)?)
     } else {
         client
     };
 
-    client.build().unwrap()
+    Ok(client.build()?)
 }
 
 fn openai_chat_header_map(token: &str) -> HeaderMap {
@@ -3,9 +3,24 @@
 /// Load account from database and map them to AccountVisitor
 pub mod account_manager;
 
+/// The affinity between the conversations and the accounts, used to hit the prompt cache
+pub mod affinity;
+
+/// The time to first token and error rate of the accounts, used to prefer the faster ones
+pub mod account_stats;
+
+/// Probe the accounts and disable the dead ones
+pub mod account_tester;
+
 /// Get the reqwest client with the proxy and endpoint
 pub mod get_reqwest_client;
 
 /// The channel manager, which is responsible for managing the buffer and sending the response to the client
 pub mod counter;
+
+/// The rate limit reported by the endpoint, used to skip the nearly exhausted accounts
+pub mod rate_limit;
+
+/// The fair queue of the requests waiting for a free account
+pub mod request_queue;
 pub mod sse;
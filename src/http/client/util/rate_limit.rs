@@ -0,0 +1,149 @@
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use reqwest::header::HeaderMap;
+
+/// The account is skipped when the remaining requests or tokens are less than this ratio of the limit.
+const RESERVE_RATIO: f64 = 0.02;
+
+/// The rate limit reported by the endpoint in the `x-ratelimit-*` headers of the last response.
+/// # Fields
+/// - limit_requests: The max requests in the window.
+/// - remaining_requests: The remaining requests before the reset.
+/// - requests_reset_at: When the remaining requests will be reset.
+/// - limit_tokens: The max tokens in the window.
+/// - remaining_tokens: The remaining tokens before the reset.
+/// - tokens_reset_at: When the remaining tokens will be reset.
+#[derive(Debug, Default, Clone)]
+pub struct RateLimitInfo {
+    pub limit_requests: Option<u64>,
+    pub remaining_requests: Option<u64>,
+    pub requests_reset_at: Option<Instant>,
+    pub limit_tokens: Option<u64>,
+    pub remaining_tokens: Option<u64>,
+    pub tokens_reset_at: Option<Instant>,
+}
+
+/// The rate limit state of an account, which is updated after each response.
+#[derive(Debug, Default)]
+pub struct RateLimitState {
+    inner: Mutex<RateLimitInfo>,
+}
+
+impl RateLimitState {
+    /// Record the rate limit headers of the response, the values that are not in the
+    /// headers are kept, so an endpoint without these headers is never throttled.
+    pub fn update(&self, headers: &HeaderMap) {
+        let now = Instant::now();
+        let number = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|x| x.to_str().ok())
+                .and_then(|x| x.parse::<u64>().ok())
+        };
+        let reset = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|x| x.to_str().ok())
+                .and_then(parse_duration)
+                .map(|x| now + x)
+        };
+
+        let mut info = self.inner.lock();
+        if let Some(limit) = number("x-ratelimit-limit-requests") {
+            info.limit_requests = Some(limit);
+        }
+        if let Some(remaining) = number("x-ratelimit-remaining-requests") {
+            info.remaining_requests = Some(remaining);
+            info.requests_reset_at = reset("x-ratelimit-reset-requests");
+        }
+        if let Some(limit) = number("x-ratelimit-limit-tokens") {
+            info.limit_tokens = Some(limit);
+        }
+        if let Some(remaining) = number("x-ratelimit-remaining-tokens") {
+            info.remaining_tokens = Some(remaining);
+            info.tokens_reset_at = reset("x-ratelimit-reset-tokens");
+        }
+    }
+
+    pub fn info(&self) -> RateLimitInfo {
+        self.inner.lock().clone()
+    }
+
+    /// Check if the account is nearly exhausted and its limit is not reset yet.
+    pub fn is_exhausted(&self) -> bool {
+        let info = self.inner.lock();
+        let now = Instant::now();
+
+        is_exhausted(info.limit_requests, info.remaining_requests, info.requests_reset_at, now)
+            || is_exhausted(info.limit_tokens, info.remaining_tokens, info.tokens_reset_at, now)
+    }
+}
+
+fn is_exhausted(limit: Option<u64>, remaining: Option<u64>, reset_at: Option<Instant>, now: Instant) -> bool {
+    let Some(remaining) = remaining else {
+        return false;
+    };
+
+    // The limit is assumed to be reset if the endpoint does not tell when.
+    if reset_at.is_none_or(|x| x <= now) {
+        return false;
+    }
+
+    let reserve = limit.map_or(0.0, |x| x as f64 * RESERVE_RATIO);
+    remaining == 0 || (remaining as f64) < reserve
+}
+
+/// Parse the duration in the reset headers, e.g. `1s`, `6m0s`, `20ms` or `1h2m3.5s`.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let mut total = 0.0;
+    let mut rest = value.trim();
+
+    if rest.is_empty() {
+        return None;
+    }
+
+    while !rest.is_empty() {
+        let split = rest.find(|x: char| x.is_ascii_alphabetic())?;
+        let (number, unit_and_rest) = rest.split_at(split);
+        let number = number.parse::<f64>().ok().filter(|x| x.is_finite() && *x >= 0.0)?;
+
+        let unit_length = unit_and_rest
+            .find(|x: char| !x.is_ascii_alphabetic())
+            .unwrap_or(unit_and_rest.len());
+        let (unit, next) = unit_and_rest.split_at(unit_length);
+
+        total += number * match unit {
+            "h" => 3600.0,
+            "m" => 60.0,
+            "s" => 1.0,
+            "ms" => 0.001,
+            _ => return None,
+        };
+        rest = next;
+    }
+
+    Duration::try_from_secs_f64(total).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("1s"), Some(Duration::from_secs(1)));
+        assert_eq!(parse_duration("6m0s"), Some(Duration::from_secs(360)));
+        assert_eq!(parse_duration("20ms"), Some(Duration::from_millis(20)));
+        assert_eq!(parse_duration("1h2m3.5s"), Some(Duration::from_secs_f64(3723.5)));
+    }
+
+    #[test]
+    fn test_parse_invalid_duration() {
+        assert_eq!(parse_duration("-1s"), None);
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("inf"), None);
+        assert_eq!(parse_duration(&format!("{}s", "9".repeat(400))), None);
+        assert_eq!(parse_duration("1d"), None);
+    }
+}
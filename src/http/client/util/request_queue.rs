@@ -0,0 +1,217 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Arc;
+
+use hashbrown::HashMap;
+use parking_lot::Mutex;
+use tokio::sync::Notify;
+
+/// The queue of the requests that are waiting for a free account.
+/// The requests are put into the lanes by their priority, only the head of each lane
+/// waits for the account pool, the others wait for their turn.
+/// The turns of a lane are given to the users in round-robin, so a user with many waiting
+/// requests can not starve the others.
+#[derive(Default)]
+pub struct RequestQueue {
+    state: Mutex<QueueState>,
+}
+
+#[derive(Default)]
+struct QueueState {
+    next_id: u64,
+    lanes: BTreeMap<i32, Lane>,
+    tickets: HashMap<u64, Arc<Notify>>,
+}
+
+#[derive(Default)]
+struct Lane {
+    head: Option<u64>,
+    /// The users that have waiting tickets, in the order of their next turn.
+    users: VecDeque<(i32, VecDeque<u64>)>,
+}
+
+impl Lane {
+    /// Give the turn to the first ticket of the next user, the user is moved to the back.
+    fn promote(&mut self, tickets: &HashMap<u64, Arc<Notify>>) {
+        self.head = None;
+
+        let Some((user_id, mut waiting)) = self.users.pop_front() else {
+            return;
+        };
+
+        if let Some(id) = waiting.pop_front() {
+            self.head = Some(id);
+            if let Some(notify) = tickets.get(&id) {
+                notify.notify_one();
+            }
+        }
+
+        if !waiting.is_empty() {
+            self.users.push_back((user_id, waiting));
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.head.is_none() && self.users.is_empty()
+    }
+}
+
+/// The place of a request in the queue, it leaves the queue when dropped.
+pub struct QueueTicket<'a> {
+    queue: &'a RequestQueue,
+    id: u64,
+    priority: i32,
+    notify: Arc<Notify>,
+}
+
+impl RequestQueue {
+    /// Join the lane of the priority, `None` if the queue is full.
+    pub fn enter(&self, user_id: i32, priority: i32, capacity: usize) -> Option<QueueTicket<'_>> {
+        let mut state = self.state.lock();
+        if state.tickets.len() >= capacity {
+            return None;
+        }
+
+        let id = state.next_id;
+        state.next_id += 1;
+
+        let notify = Arc::new(Notify::new());
+        state.tickets.insert(id, notify.clone());
+
+        let QueueState { lanes, tickets, .. } = &mut *state;
+        let lane = lanes.entry(priority).or_default();
+        match lane.users.iter_mut().find(|(x, _)| *x == user_id) {
+            Some((_, waiting)) => waiting.push_back(id),
+            None => lane.users.push_back((user_id, VecDeque::from([id]))),
+        }
+
+        if lane.head.is_none() {
+            lane.promote(tickets);
+        }
+
+        Some(QueueTicket { queue: self, id, priority, notify })
+    }
+
+    /// The number of the requests in the queue.
+    pub fn len(&self) -> usize {
+        self.state.lock().tickets.len()
+    }
+
+    /// Check if no request of the priority is waiting.
+    pub fn is_lane_empty(&self, priority: i32) -> bool {
+        self.state.lock().lanes.get(&priority).is_none_or(|x| x.is_empty())
+    }
+}
+
+impl QueueTicket<'_> {
+    /// Wait until this ticket is the head of its lane.
+    pub async fn wait_turn(&self) {
+        loop {
+            let is_head = self
+                .queue
+                .state
+                .lock()
+                .lanes
+                .get(&self.priority)
+                .is_some_and(|x| x.head == Some(self.id));
+            if is_head {
+                return;
+            }
+
+            self.notify.notified().await;
+        }
+    }
+}
+
+impl Drop for QueueTicket<'_> {
+    fn drop(&mut self) {
+        let mut state = self.queue.state.lock();
+        state.tickets.remove(&self.id);
+
+        let QueueState { lanes, tickets, .. } = &mut *state;
+        let Some(lane) = lanes.get_mut(&self.priority) else {
+            return;
+        };
+
+        if lane.head == Some(self.id) {
+            lane.promote(tickets);
+        } else {
+            for (_, waiting) in lane.users.iter_mut() {
+                waiting.retain(|x| *x != self.id);
+            }
+            lane.users.retain(|(_, waiting)| !waiting.is_empty());
+        }
+
+        if lane.is_empty() {
+            lanes.remove(&self.priority);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn head(queue: &RequestQueue, priority: i32) -> Option<u64> {
+        queue.state.lock().lanes.get(&priority).and_then(|x| x.head)
+    }
+
+    #[test]
+    fn test_round_robin_between_users() {
+        let queue = RequestQueue::default();
+        let a1 = queue.enter(1, 0, 10).unwrap();
+        let a2 = queue.enter(1, 0, 10).unwrap();
+        let a3 = queue.enter(1, 0, 10).unwrap();
+        let b1 = queue.enter(2, 0, 10).unwrap();
+        assert_eq!(head(&queue, 0), Some(a1.id));
+
+        drop(a1);
+        assert_eq!(head(&queue, 0), Some(a2.id));
+        drop(a2);
+        assert_eq!(head(&queue, 0), Some(b1.id));
+        drop(b1);
+        assert_eq!(head(&queue, 0), Some(a3.id));
+        drop(a3);
+        assert!(queue.is_lane_empty(0));
+        assert_eq!(queue.len(), 0);
+    }
+
+    #[test]
+    fn test_promote_on_drop() {
+        let queue = RequestQueue::default();
+        let a1 = queue.enter(1, 0, 10).unwrap();
+        let b1 = queue.enter(2, 0, 10).unwrap();
+        let c1 = queue.enter(3, 0, 10).unwrap();
+
+        // A waiting ticket leaves without changing the head.
+        drop(b1);
+        assert_eq!(head(&queue, 0), Some(a1.id));
+        assert_eq!(queue.len(), 2);
+
+        drop(a1);
+        assert_eq!(head(&queue, 0), Some(c1.id));
+    }
+
+    #[test]
+    fn test_lanes_of_priorities() {
+        let queue = RequestQueue::default();
+        let low = queue.enter(1, 0, 10).unwrap();
+        let high = queue.enter(2, 1, 10).unwrap();
+        assert_eq!(head(&queue, 0), Some(low.id));
+        assert_eq!(head(&queue, 1), Some(high.id));
+
+        drop(high);
+        assert!(queue.is_lane_empty(1));
+        assert!(!queue.is_lane_empty(0));
+    }
+
+    #[test]
+    fn test_reject_when_full() {
+        let queue = RequestQueue::default();
+        let first = queue.enter(1, 0, 2).unwrap();
+        let _second = queue.enter(2, 1, 2).unwrap();
+        assert!(queue.enter(3, 0, 2).is_none());
+
+        drop(first);
+        assert!(queue.enter(3, 0, 2).is_some());
+    }
+}
@@ -0,0 +1,146 @@
+//! The prometheus metrics of this app, all the metrics are registered in the
+//! default registry and exported by the `/metrics` endpoint.
+
+use std::sync::LazyLock;
+
+use prometheus::{
+    register_counter_vec, register_histogram_vec, register_int_counter_vec, register_int_gauge,
+    register_int_gauge_vec, CounterVec, Encoder, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec,
+    TextEncoder,
+};
+
+use crate::data::config::entity::runtime_data::GlobalData;
+
+/// The model label of the requests with a model not in `model_info`, so that the clients can
+/// not create unlimited label values.
+const UNKNOWN_MODEL: &str = "unknown";
+
+/// The buckets of the latency histograms, in seconds.
+const LATENCY_BUCKETS: &[f64] = &[0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 20.0, 30.0, 60.0, 120.0, 300.0];
+
+/// The requests by model, endpoint and outcome.
+pub static REQUESTS_TOTAL: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "gpt_cat_requests_total",
+        "The number of chat requests by model, endpoint and outcome.",
+        &["model", "endpoint", "outcome"]
+    )
+    .unwrap()
+});
+
+/// The time to first token of each account.
+pub static TIME_TO_FIRST_TOKEN: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "gpt_cat_time_to_first_token_seconds",
+        "The time from sending the upstream request to the first byte sent to the client.",
+        &["account_id", "endpoint"],
+        LATENCY_BUCKETS.to_vec()
+    )
+    .unwrap()
+});
+
+/// The total latency of each account.
+pub static REQUEST_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "gpt_cat_request_duration_seconds",
+        "The total latency of the upstream request.",
+        &["account_id", "endpoint"],
+        LATENCY_BUCKETS.to_vec()
+    )
+    .unwrap()
+});
+
+/// The retries used from `number_can_retries`.
+pub static RETRIES_TOTAL: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "gpt_cat_retries_total",
+        "The number of retries used on another account after a failed upstream request.",
+        &["model", "endpoint"]
+    )
+    .unwrap()
+});
+
+/// The in-flight slots of each account, this is refreshed when the metrics are scraped.
+pub static POOL_IN_FLIGHT: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "gpt_cat_pool_in_flight",
+        "The number of in-flight slots of each account in the account pool.",
+        &["account_id", "endpoint"]
+    )
+    .unwrap()
+});
+
+/// The requests waiting for a free account, this is refreshed when the metrics are scraped.
+pub static QUEUE_LENGTH: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "gpt_cat_queue_length",
+        "The number of requests waiting for a free account."
+    )
+    .unwrap()
+});
+
+/// The tokens billed by the token meter.
+pub static BILLED_TOKENS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "gpt_cat_billed_tokens_total",
+        "The number of tokens billed to users.",
+        &["model", "kind"]
+    )
+    .unwrap()
+});
+
+/// The revenue billed by the token meter.
+pub static BILLED_REVENUE: LazyLock<CounterVec> = LazyLock::new(|| {
+    register_counter_vec!(
+        "gpt_cat_billed_revenue_total",
+        "The revenue billed to users.",
+        &["model"]
+    )
+    .unwrap()
+});
+
+/// The cost charged by the endpoints, only the requests with a known cost are counted.
+pub static BILLED_COST: LazyLock<CounterVec> = LazyLock::new(|| {
+    register_counter_vec!(
+        "gpt_cat_billed_cost_total",
+        "The cost charged by the endpoints.",
+        &["model", "endpoint"]
+    )
+    .unwrap()
+});
+
+/// The pre-handler rejections by handler.
+pub static PRE_HANDLER_REJECTIONS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "gpt_cat_pre_handler_rejections_total",
+        "The number of requests rejected by each pre-handler.",
+        &["handler"]
+    )
+    .unwrap()
+});
+
+/// The model label of a request, the model from the client is only used if it is configured.
+pub fn model_label<'a>(data: &GlobalData, model: &'a str) -> &'a str {
+    if data.model_info.read().has_model(model) {
+        model
+    } else {
+        UNKNOWN_MODEL
+    }
+}
+
+/// Refresh the gauges read from the account pool and encode all the metrics
+/// in the prometheus text format.
+pub fn gather_metrics(data: &GlobalData) -> anyhow::Result<String> {
+    POOL_IN_FLIGHT.reset();
+    for pool in data.account_pool.load().iter() {
+        POOL_IN_FLIGHT
+            .with_label_values(&[pool.get_account_id().to_string(), pool.get_endpoint().to_string()])
+            .set(pool.in_flight() as i64);
+    }
+    QUEUE_LENGTH.set(data.request_queue.len() as i64);
+
+    let mut buffer = Vec::new();
+    TextEncoder::new().encode(&prometheus::gather(), &mut buffer)?;
+
+    Ok(String::from_utf8(buffer)?)
+}
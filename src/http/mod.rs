@@ -2,4 +2,5 @@
 
 #[macro_use]
 pub mod server;
-pub mod client;
\ No newline at end of file
+pub mod client;
+pub mod metrics;
\ No newline at end of file
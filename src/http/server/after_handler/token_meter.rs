@@ -5,9 +5,11 @@ use crate::http::client::client_sender::channel_manager::ChannelBufferManager;
 use crate::http::server::after_handler::{ClientEndAfterHandlerImpl, ClientEndContext};
 use color_eyre::owo_colors::OwoColorize;
 use log::{error, info};
+use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::Decimal;
 use tiktoken_rs::{cl100k_base, o200k_base, CoreBPE};
-use crate::data::config::entity::model_price::ModelPriceValue;
+use crate::data::config::entity::model_price::TokenUsage;
+use crate::http::metrics::{BILLED_COST, BILLED_REVENUE, BILLED_TOKENS};
 
 #[derive(Default, Clone)]
 pub struct TokenMeterHandler;
@@ -35,7 +37,8 @@ impl ClientEndAfterHandlerImpl for TokenMeterHandler {
                 user_token += tick_token.encode_with_special_tokens(x).len();
             }
             info!(
-                "User input: {}, AI output: {}",
+                "[{}] User input: {}, AI output: {}",
+                context.sender.request_id,
                 user_input.truecolor(242, 127, 10),
                 buffer.purple()
             );
@@ -44,7 +47,7 @@ impl ClientEndAfterHandlerImpl for TokenMeterHandler {
 
         let ai_token = tick_token.encode_with_special_tokens(buffer).len();
 
-        info!("Use of user token: {}, AI token: {}", user_token, ai_token);
+        info!("[{}] Use of user token: {}, AI token: {}", context.sender.request_id, user_token, ai_token);
         if let Some(price) = context
             .data
             .model_price
@@ -52,55 +55,100 @@ impl ClientEndAfterHandlerImpl for TokenMeterHandler {
             .get(&context.sender.request.model)
         {
             info!(
-                "model: {}, price: {:?}",
+                "[{}] model: {}, price: {:?}",
+                context.sender.request_id,
                 context.sender.request.model,
                 price
             );
             let price = price.clone();
 
-            let query = match price {
-                ModelPriceValue::PerToken(token) => {
-                    sqlx::query!(
-                        "
-                            INSERT INTO
-                            usage_list (user_id, input_tokens, output_tokens, input_token_price, output_token_price)
-                            VALUES
-                            ($1, $2, $3, $4, $5)
-                        ",
-                        context.user_id,
-                        user_token as i32,
-                        ai_token as i32,
-                        token.input_price,
-                        token.output_price
-                    )
-                }
-                ModelPriceValue::PerTimes(times) => {
-                    sqlx::query!(
-                        "
-                            INSERT INTO
-                            usage_list (user_id, input_tokens, output_tokens, input_token_price, output_token_price)
-                            VALUES
-                            ($1, $2, $3, $4, $5)
-                        ",
-                        context.user_id,
-                        1,
-                        0,
-                        times.price,
-                        Decimal::new(0, 0)
-                    )
-                }
+            // The usage reported by the endpoint is preferred, which splits out the cached input and the
+            // reasoning, otherwise it is counted from the messages.
+            let usage = context.sender.usage().unwrap_or(TokenUsage {
+                input: user_token as u64,
+                output: ai_token as u64,
+                ..Default::default()
+            });
+            let revenue = price.calculate(&usage);
+
+            // The model is the one sent to the endpoint, which may be changed by the model mapping.
+            let account_id = context.response_data.account_id;
+            let endpoint = context.response_data.use_endpoint.to_string();
+            let model = context.sender.request.model.as_str();
+            let cost = context
+                .data
+                .model_cost
+                .read()
+                .get(account_id, &context.response_data.use_endpoint, model)
+                .map(|x| x.calculate(&usage));
+
+            let per_token = price.per_token(usage.prompt());
+            let (billed, input_price, cached_input_price, output_price, reasoning_price) = match per_token {
+                Some(token) => (
+                    usage,
+                    token.input_price,
+                    token.get_cached_input_price(),
+                    token.output_price,
+                    token.get_reasoning_price(),
+                ),
+                None => (
+                    TokenUsage { input: 1, ..Default::default() },
+                    revenue,
+                    Decimal::ZERO,
+                    Decimal::ZERO,
+                    Decimal::ZERO,
+                ),
             };
+
+            let query = sqlx::query!(
+                "
+                    INSERT INTO
+                    usage_list (user_id, input_tokens, output_tokens, input_token_price, output_token_price,
+                                cached_input_tokens, cached_input_token_price, reasoning_tokens, reasoning_token_price,
+                                account_id, endpoint, model, cost)
+                    VALUES
+                    ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+                ",
+                context.user_id,
+                billed.input as i32,
+                billed.output as i32,
+                input_price,
+                output_price,
+                billed.cached_input as i32,
+                cached_input_price,
+                billed.reasoning as i32,
+                reasoning_price,
+                account_id,
+                endpoint,
+                model,
+                cost
+            );
             let insert_id = query
                 .execute(&context.data.data_base)
                 .await
                 .map_err(|err| format!("Error when insert usage list: {}", err))?;
 
+            if per_token.is_some() {
+                BILLED_TOKENS.with_label_values(&[model, "input"]).inc_by(usage.input);
+                BILLED_TOKENS.with_label_values(&[model, "cached_input"]).inc_by(usage.cached_input);
+                BILLED_TOKENS.with_label_values(&[model, "output"]).inc_by(usage.output);
+                BILLED_TOKENS.with_label_values(&[model, "reasoning"]).inc_by(usage.reasoning);
+            }
+            BILLED_REVENUE
+                .with_label_values(&[model])
+                .inc_by(revenue.to_f64().unwrap_or_default());
+            if let Some(cost) = cost {
+                BILLED_COST
+                    .with_label_values(&[model, endpoint.as_str()])
+                    .inc_by(cost.to_f64().unwrap_or_default());
+            }
+
             info!(
-                "Insert usage last insert id: {:?}, current endpoint: {}",
-                insert_id, context.response_data.use_endpoint
+                "[{}] Insert usage last insert id: {:?}, current endpoint: {}",
+                context.sender.request_id, insert_id, context.response_data.use_endpoint
             );
         } else {
-            error!("Model not found: {}", context.sender.request.model);
+            error!("[{}] Model not found: {}", context.sender.request_id, context.sender.request.model);
         }
 
         Ok(())
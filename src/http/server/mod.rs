@@ -5,6 +5,7 @@ use std::sync::Arc;
 
 use crate::http::server::after_handler::token_meter::TokenMeterHandler;
 use crate::http::server::pre_handler::command::command_handler::CommandJoinPreHandler;
+use crate::http::server::pre_handler::model_access::ModelAccessHandler;
 use crate::http::server::pre_handler::model_filter::ModelFilterHandler;
 use crate::http::server::pre_handler::title_catcher::TitleCatchHandler;
 use crate::http::server::pre_handler::user_key_handler::UserKeyHandler;
@@ -21,6 +22,9 @@ pub mod after_handler;
 /// The web server
 pub mod web;
 
+/// The graceful shutdown
+pub mod shutdown;
+
 /// Define the pre-handler pipeline, this pipeline running when a
 /// client request is coming. **Note that** the order of the handler
 /// is important, the handler will be executed in the order of the
@@ -31,7 +35,8 @@ impl_client_join_handler![
     UserKeyHandler,
     UserIDHandler,
     TitleCatchHandler,
-    CommandJoinPreHandler
+    CommandJoinPreHandler,
+    ModelAccessHandler
 ];
 
 /// Define the after-handler pipeline, because the after-handler is
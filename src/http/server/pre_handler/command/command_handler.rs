@@ -46,7 +46,7 @@ impl ClientJoinPreHandlerImpl for CommandJoinPreHandler {
         if let Some(message) = message {
             let args: Vec<&str> = message.split_whitespace().collect();
             let command = args[0].trim_start_matches('/');
-            info!("User {:?} use command: {}", context.user_id, command);
+            info!("[{}] User {:?} use command: {}", context.sender.request_id, context.user_id, command);
 
             if command == "help" || command == "h" {
                 context.sender.send_text(HELP_MESSAGE.deref(), true).await?;
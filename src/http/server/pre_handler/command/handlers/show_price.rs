@@ -2,7 +2,7 @@ use anyhow::anyhow;
 use rust_decimal::Decimal;
 use cat_macro::describe;
 use crate::commandline::handlers::describer::CommandDescription;
-use crate::data::config::entity::model_price::ModelPriceValue;
+use crate::data::config::entity::model_price::{ModelPerToken, ModelPriceValue};
 use crate::http::client::client_sender::channel_manager::ChannelSender;
 use crate::http::server::pre_handler::{ClientJoinContext, PreHandlerResult};
 use crate::http::server::pre_handler::command::handlers::CommandHandler;
@@ -22,6 +22,10 @@ impl CommandHandler for ShowPriceHandler {
     async fn execute(&self, context: &mut ClientJoinContext<'_>, args: &Vec<&str>) -> anyhow::Result<PreHandlerResult> {
         let model_name = args.get(0).ok_or(anyhow!("Missing model name"))?;
         let model_name = model_name.to_lowercase();
+        if context.user_id.is_none() {
+            return Err(anyhow!("Missing user id"));
+        }
+        let allow_list = &context.allow_list;
 
         let mut price_message = String::from("###  💰模型价格\n");
 
@@ -35,27 +39,31 @@ impl CommandHandler for ShowPriceHandler {
         }
 
         price.iter().for_each(|(model, price)| {
-            if model.contains(&model_name) {
+            if model.contains(&model_name) && allow_list.allows(model) {
                 if is_empty {
                     is_empty = false;
-                    price_message.push_str("| 模型名称 | 输入价格[元/千token & 元/次] | 输出价格(元/千token) |\n");
-                    price_message.push_str("| --- | --- | --- |\n");
+                    price_message.push_str("| 模型名称 | 输入价格[元/千token & 元/次] | 缓存输入价格(元/千token) | 输出价格(元/千token) | 推理价格(元/千token) |\n");
+                    price_message.push_str("| --- | --- | --- | --- | --- |\n");
                 }
                 match price {
                     ModelPriceValue::PerToken(token) => {
-                        //当前的模型价格是元每个token，把它转换为元每千token
-                        let input_price_per_1000_tokens = token.input_price.saturating_mul(Decimal::new(1000, 0));
-                        let output_price_per_1000_tokens = token.output_price.saturating_mul(Decimal::new(1000, 0));
-                        price_message.push_str(&format!(
-                            "| {} | {:} | {:} |\n",
-                            model,
-                            input_price_per_1000_tokens,
-                            output_price_per_1000_tokens
-                        ));
+                        price_message.push_str(&per_token_row(model, token));
+                    }
+                    ModelPriceValue::Tiered(tiered) => {
+                        let mut min_input_tokens = 0;
+                        for tier in tiered.tiers.iter() {
+                            let name = match tier.max_input_tokens {
+                                Some(max) => format!("{}（输入≤{}token）", model, max),
+                                None if min_input_tokens == 0 => model.to_string(),
+                                None => format!("{}（输入>{}token）", model, min_input_tokens),
+                            };
+                            price_message.push_str(&per_token_row(&name, &tier.price));
+                            min_input_tokens = tier.max_input_tokens.unwrap_or(min_input_tokens);
+                        }
                     }
                     ModelPriceValue::PerTimes(times) => {
                         price_message.push_str(&format!(
-                            "| {} | {:} | - |\n",
+                            "| {} | {:} | - | - | - |\n",
                             model,
                             times.price
                         ));
@@ -72,4 +80,17 @@ impl CommandHandler for ShowPriceHandler {
 
         Ok(PreHandlerResult::Return)
     }
+}
+
+fn per_token_row(name: &str, token: &ModelPerToken) -> String {
+    //当前的模型价格是元每个token，把它转换为元每千token
+    let per_1000_tokens = |price: Decimal| price.saturating_mul(Decimal::new(1000, 0));
+    format!(
+        "| {} | {:} | {:} | {:} | {:} |\n",
+        name,
+        per_1000_tokens(token.input_price),
+        per_1000_tokens(token.get_cached_input_price()),
+        per_1000_tokens(token.output_price),
+        per_1000_tokens(token.get_reasoning_price())
+    )
 }
\ No newline at end of file
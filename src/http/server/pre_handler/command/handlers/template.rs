@@ -74,7 +74,7 @@ impl CommandHandler for TemplateHandler {
 
         if template_name == "end-template" {
             let prompt_messages = serde_json::to_string(&context.sender.request.messages).map_err(|e| {
-                error!("Error when serializing prompt messages: {:?}", e);
+                error!("[{}] Error when serializing prompt messages: {:?}", context.sender.request_id, e);
                 anyhow!("Error when serializing prompt messages!")
             })?;
             let &template_name = args.get(1).ok_or(anyhow!("Missing custom template name."))?;
@@ -118,21 +118,21 @@ impl CommandHandler for TemplateHandler {
             }
 
             let public_command = public_command.map_err(|e| {
-                error!("Error when fetching public command: {:?}", e);
+                error!("[{}] Error when fetching public command: {:?}", context.sender.request_id, e);
                 anyhow!("Error when fetching command!")
             })?;
 
             apply_template(context, public_command.prompt.as_str())?;
         }else {
             let private_command = private_command.map_err(|e| {
-                error!("Error when fetching private command: {:?}", e);
+                error!("[{}] Error when fetching private command: {:?}", context.sender.request_id, e);
                 anyhow!("Error when fetching command!")
             })?;
 
             apply_template(context, private_command.prompt.as_str())?;
         }
 
-        info!("User {:?} used template {}", context.user_id, template_name);
+        info!("[{}] User {:?} used template {}", context.sender.request_id, context.user_id, template_name);
         Ok(PreHandlerResult::Pass)
     }
 }
@@ -149,7 +149,7 @@ fn apply_template(context: &mut ClientJoinContext, command: &str) -> Result<()>
 
     let mut prompt_messages = serde_json::from_str::<Vec<Message>>(command)
         .map_err(|e| {
-            error!("Error when parsing template: {:?}", e);
+            error!("[{}] Error when parsing template: {:?}", context.sender.request_id, e);
             anyhow!("Error when parsing template!")
         })?;
 
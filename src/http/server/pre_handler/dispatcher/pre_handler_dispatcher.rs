@@ -1,4 +1,5 @@
 use crate::http::client::client_sender::channel_manager::{ChannelSender, ResponsiveError};
+use crate::http::metrics::PRE_HANDLER_REJECTIONS;
 use crate::http::server::pre_handler::{ClientJoinContext, ClientJoinPreHandlerImpl, PreHandlerResult};
 use crate::http::server::ClientJoinPreHandler;
 use log::error;
@@ -29,7 +30,8 @@ impl ClientJoinHandlers {
                         message: error.to_string(),
                         suggestion: None,
                     });
-                    error!("ClientJoinHandlers: {}", error.to_string());
+                    PRE_HANDLER_REJECTIONS.with_label_values(&[handler.name()]).inc();
+                    error!("[{}] ClientJoinHandlers: {}", context.sender.request_id, error.to_string());
                     break;
                 }
                 Ok(PreHandlerResult::Pass) => {}
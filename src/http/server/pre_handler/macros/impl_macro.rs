@@ -21,6 +21,17 @@ macro_rules! impl_client_join_handler {
             }
         }
 
+        impl ClientJoinPreHandler {
+            /// The name of the handler, used in logs and metrics.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(
+                        ClientJoinPreHandler::$variant(_) => stringify!($variant),
+                    )*
+                }
+            }
+        }
+
         pub fn get_client_join_handler() -> ClientJoinHandlers {
             ClientJoinHandlers::new(vec![
                 $(
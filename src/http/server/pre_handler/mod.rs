@@ -1,4 +1,5 @@
 use crate::data::config::entity::runtime_data::GlobalData;
+use crate::data::config::entity::model_access::ModelAllowList;
 use crate::http::client::client_sender::channel_manager::ClientSender;
 use anyhow::Result;
 use ntex::http::HeaderMap;
@@ -7,6 +8,7 @@ use ntex::http::HeaderMap;
 mod macros;
 pub mod dispatcher;
 pub(super) mod model_filter;
+pub(super) mod model_access;
 pub(super) mod title_catcher;
 pub(super) mod user_key_handler;
 pub(super) mod userid_handler;
@@ -17,6 +19,8 @@ pub struct ClientJoinContext<'a> {
     pub sender: ClientSender,
     pub user_key: Option<String>,
     pub user_id: Option<i32>,
+    pub priority: i32,
+    pub allow_list: ModelAllowList,
     pub request_header: &'a HeaderMap,
     pub global_data: &'static GlobalData,
 }
@@ -0,0 +1,27 @@
+use anyhow::anyhow;
+
+use crate::http::server::pre_handler::{ClientJoinContext, ClientJoinPreHandlerImpl, PreHandlerResult};
+
+/// Reject the request if the model is not allowed by the group of the user.
+#[derive(Default, Clone)]
+pub(crate) struct ModelAccessHandler;
+
+impl ClientJoinPreHandlerImpl for ModelAccessHandler {
+    async fn client_join<'a>(
+        &'a self,
+        context: &mut ClientJoinContext<'a>,
+    ) -> anyhow::Result<PreHandlerResult> {
+        if context.user_id.is_none() {
+            return Err(anyhow!("未找到KEY，请在您的客户端中设置KEY"));
+        }
+
+        if !context.allow_list.allows(&context.sender.request.model) {
+            return Err(anyhow!(
+                "您所在的用户组无权使用模型: '{}'",
+                context.sender.request.model
+            ));
+        }
+
+        Ok(PreHandlerResult::Pass)
+    }
+}
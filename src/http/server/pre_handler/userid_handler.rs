@@ -1,6 +1,10 @@
+use std::time::Duration;
+
 use anyhow::anyhow;
+use log::error;
 
-use crate::data::database::entity::user::DataBaseUser;
+use crate::data::config::entity::runtime_data::GlobalData;
+use crate::data::database::user_key::{find_key_owner, mask_key, touch_key, KeyOwner};
 use crate::http::server::pre_handler::{ClientJoinContext, ClientJoinPreHandlerImpl, PreHandlerResult};
 
 #[derive(Default, Clone)]
@@ -11,41 +15,64 @@ impl ClientJoinPreHandlerImpl for UserIDHandler {
         &'a self,
         context: &mut ClientJoinContext<'a>,
     ) -> anyhow::Result<PreHandlerResult> {
-        let user_id = if let Some(auth) = &context.user_key {
-
-            let user: Result<DataBaseUser, sqlx::Error> = sqlx::query_as!(
-                DataBaseUser,
-                r#"SELECT * FROM "user" WHERE api_key = $1 LIMIT 1"#,
-                auth
-            )
-                .fetch_one(&context.global_data.data_base)
-                .await;
-
-            match user {
-                Err(_) => {//todo handle error
-                    // return Err(anyhow!("Invalid key: {}, please ensure that you have already put a key.", auth));
-                    return Err(anyhow!(
-                        "无效的Key: {}, 请输入正确的Key或检查拼写是否正确",
-                        auth
-                    ));
-                }
-                Ok(user) => {
-                    if user.is_active {
-                        user.id
-                    } else {
-                        // return Err(anyhow!("Account is inactive, try to ensure your account has not ran out of your usage limit then contact THE cat."));
-                        return Err(anyhow!(
-                            "帐户处于非活动状态，请尝试检查您的账户是否已超出额度"
-                        ));
-                    }
-                }
-            }
+        let owner = if let Some(auth) = &context.user_key {
+            find_key_owner_checked(context.global_data, auth).await?
         } else {
             // return Err(anyhow!("KEY not found, please set a key in your client."));
             return Err(anyhow!("未找到KEY，请在您的客户端中设置KEY"));
         };
 
-        context.user_id.replace(user_id);
+        context.user_id.replace(owner.user_id);
+        context.priority = owner.priority;
+        context.allow_list = owner.allow_list;
         Ok(PreHandlerResult::Pass)
     }
 }
+
+/// Find the owner of the key, the user should be active and the key should not be expired or revoked.
+/// The owner is cached for `auth_cache_ttl` seconds, the last used time of the key is
+/// only updated when the cache is missed.
+pub(crate) async fn find_key_owner_checked(global_data: &GlobalData, auth: &str) -> anyhow::Result<KeyOwner> {
+    let ttl = Duration::from_secs(global_data.config.read().auth_cache_ttl);
+    let owner = match global_data.auth_cache.get(auth, ttl) {
+        Some(owner) => Some(owner),
+        None => {
+            let generation = global_data.auth_cache.generation();
+            let owner = find_key_owner(&global_data.data_base, auth).await?;
+            if let Some(owner) = &owner {
+                if let Err(e) = touch_key(&global_data.data_base, owner.key_id).await {
+                    error!("Error when update last used time of key {}: {}", owner.key_id, e);
+                }
+                if !ttl.is_zero() {
+                    global_data.auth_cache.insert(auth, owner.clone(), generation);
+                }
+            }
+            owner
+        }
+    };
+
+    let Some(owner) = owner else {
+        // return Err(anyhow!("Invalid key: {}, please ensure that you have already put a key.", auth));
+        return Err(anyhow!(
+            "无效的Key: {}, 请输入正确的Key或检查拼写是否正确",
+            mask_key(auth)
+        ));
+    };
+
+    if owner.revoked {
+        return Err(anyhow!("Key已被吊销，请使用其他Key或联系管理员"));
+    }
+
+    if owner.expired {
+        return Err(anyhow!("Key已过期，请使用其他Key或联系管理员"));
+    }
+
+    if !owner.is_active {
+        // return Err(anyhow!("Account is inactive, try to ensure your account has not ran out of your usage limit then contact THE cat."));
+        return Err(anyhow!(
+            "帐户处于非活动状态，请尝试检查您的账户是否已超出额度"
+        ));
+    }
+
+    Ok(owner)
+}
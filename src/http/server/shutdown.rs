@@ -0,0 +1,104 @@
+//! Graceful shutdown of the server.
+//! When a SIGTERM/SIGINT is received or the REPL is interrupted, the server will stop
+//! accepting new connections, and wait for the spawned request tasks, including the
+//! after-handlers, to finish before exiting.
+
+use std::sync::atomic::Ordering::SeqCst;
+use std::sync::atomic::{AtomicBool, AtomicUsize};
+use std::time::Duration;
+
+use tokio::sync::Notify;
+use tokio::time::timeout;
+
+/// The tracker of the running request tasks.
+/// # Fields
+/// - running: The number of the running request tasks.
+/// - closed: Whether the server is shutting down, no new request will be tracked after closed.
+/// - finished: Notified when all the running request tasks are finished.
+/// - shutdown: Notified when a shutdown is requested by the REPL.
+#[derive(Default)]
+pub struct RequestTracker {
+    running: AtomicUsize,
+    closed: AtomicBool,
+    finished: Notify,
+    shutdown: Notify,
+}
+
+/// The guard of a running request task, the task is finished when it is dropped.
+pub struct RequestGuard<'a> {
+    tracker: &'a RequestTracker,
+}
+
+impl Drop for RequestGuard<'_> {
+    fn drop(&mut self) {
+        if self.tracker.running.fetch_sub(1, SeqCst) == 1 {
+            self.tracker.finished.notify_waiters();
+        }
+    }
+}
+
+impl RequestTracker {
+    /// Track a new request task, return `None` if the server is shutting down.
+    pub fn track(&self) -> Option<RequestGuard<'_>> {
+        if self.is_closed() {
+            return None;
+        }
+
+        self.running.fetch_add(1, SeqCst);
+        Some(RequestGuard { tracker: self })
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(SeqCst)
+    }
+
+    pub fn running(&self) -> usize {
+        self.running.load(SeqCst)
+    }
+
+    /// Request a shutdown, this can be called from any thread.
+    pub fn request_shutdown(&self) {
+        self.shutdown.notify_one();
+    }
+
+    /// Wait until a shutdown is requested by a signal or the REPL.
+    pub async fn wait_for_shutdown(&self) {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+
+            let mut terminate = signal(SignalKind::terminate()).expect("Error listening SIGTERM");
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = terminate.recv() => {}
+                _ = self.shutdown.notified() => {}
+            }
+        }
+
+        #[cfg(not(unix))]
+        {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = self.shutdown.notified() => {}
+            }
+        }
+    }
+
+    /// Stop tracking new request tasks and wait for the running ones to finish.
+    /// Return `false` if there are still running tasks after the deadline.
+    pub async fn drain(&self, deadline: Duration) -> bool {
+        self.closed.store(true, SeqCst);
+
+        let wait = async {
+            loop {
+                let finished = self.finished.notified();
+                if self.running() == 0 {
+                    break;
+                }
+                finished.await;
+            }
+        };
+
+        timeout(deadline, wait).await.is_ok()
+    }
+}
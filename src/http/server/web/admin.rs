@@ -0,0 +1,54 @@
+use std::ops::Deref;
+
+use ntex::web;
+use ntex::web::types::{Query, State};
+use ntex::web::{HttpRequest, HttpResponse, Responder};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::data::config::entity::runtime_data::{GlobalData, ServerPipeline};
+use crate::http::client::util::account_tester::{disable_dead_accounts, test_accounts};
+use crate::http::server::web::admin_auth::check_admin;
+
+/// The query of the account test.
+/// # Fields
+/// - account: The id of the account to test, all the accounts in pool will be tested if not set.
+/// - disable: Disable the accounts rejected by the endpoint if it is `1` or `true`.
+#[derive(Debug, Deserialize)]
+pub struct TestAccountQuery {
+    account: Option<i32>,
+    disable: Option<String>,
+}
+
+/// Test the accounts in pool, the same as the `test_account` command.
+/// The admin api requires the `admin_key` in config as the bearer token, and it is
+/// not found if the `admin_key` is not set.
+#[web::get("/admin/accounts/test")]
+pub async fn test_account(
+    request: HttpRequest,
+    state: State<(&'static GlobalData, &'static ServerPipeline)>,
+    query: Query<TestAccountQuery>,
+) -> impl Responder {
+    let &(data, _) = state.deref();
+    if let Err(response) = check_admin(&request, data) {
+        return response;
+    }
+
+    let results = test_accounts(data, query.account).await;
+
+    let disabled = if query.disable.as_deref().is_some_and(|x| x == "1" || x == "true") {
+        match disable_dead_accounts(data, &results).await {
+            Ok(disabled) => disabled,
+            Err(e) => {
+                return HttpResponse::InternalServerError().json(&json!({ "error": e.to_string() }));
+            }
+        }
+    } else {
+        vec![]
+    };
+
+    HttpResponse::Ok().json(&json!({
+        "results": results,
+        "disabled": disabled,
+    }))
+}
@@ -0,0 +1,24 @@
+use ntex::web::{HttpRequest, HttpResponse};
+use serde_json::json;
+
+use crate::data::config::entity::runtime_data::GlobalData;
+use crate::data::database::user_key::constant_time_eq;
+
+/// Check the `admin_key` in config as the bearer token of the request.
+/// The response to return is not found if the `admin_key` is not set, otherwise unauthorized.
+pub(super) fn check_admin(request: &HttpRequest, data: &GlobalData) -> Result<(), HttpResponse> {
+    let Some(admin_key) = data.config.read().admin_key.clone() else {
+        return Err(HttpResponse::NotFound().finish());
+    };
+
+    let key = request
+        .headers()
+        .get("Authorization")
+        .and_then(|x| x.to_str().ok())
+        .and_then(|x| x.strip_prefix("Bearer "));
+
+    match key {
+        Some(key) if constant_time_eq(key.as_bytes(), admin_key.as_bytes()) => Ok(()),
+        _ => Err(HttpResponse::Unauthorized().json(&json!({ "error": "Invalid admin key" }))),
+    }
+}
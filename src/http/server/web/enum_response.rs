@@ -28,12 +28,14 @@ impl Stream for Client {
 pub(super) async fn end(
     mut receiver: Receiver<Bytes>,
     is_stream: bool,
+    request_id: &str,
 ) -> Response {
     if is_stream {
         HttpResponse::Ok()
             .encoding(ContentEncoding::Identity)
             .content_type("text/event-stream")
             .header("Cache-Control", "no-cache")
+            .header("X-Request-Id", request_id)
             .keep_alive()
             .streaming(Client(receiver))
     }else {
@@ -46,6 +48,7 @@ pub(super) async fn end(
             .content_type("application/json")
             .encoding(ContentEncoding::Identity)
             .header("Cache-Control", "no-cache, must-revalidate")
+            .header("X-Request-Id", request_id)
             .body(back)
     }
 }
\ No newline at end of file
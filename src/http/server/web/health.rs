@@ -0,0 +1,199 @@
+use std::ops::Deref;
+use std::time::Instant;
+
+use log::error;
+use ntex::util::Bytes;
+use ntex::web;
+use ntex::web::types::{Query, State};
+use ntex::web::{HttpRequest, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+use sqlx::Connection;
+use tokio::sync::mpsc::channel;
+use uuid::Uuid;
+
+use crate::data::config::entity::runtime_data::{GlobalData, ServerPipeline};
+use crate::data::http_api::openai::openai_request::{Message, MessageContent, OpenAIRequest};
+use crate::http::client::client_sender::channel_manager::ClientSender;
+use crate::http::client::specific_responder::SpecificResponder;
+use crate::http::client::util::counter::concurrency_pool::VecGettable;
+use crate::http::server::web::admin_auth::check_admin;
+
+/// The query of the readiness check.
+/// # Fields
+/// - deep: Send a minimal probe through an account if it is `1` or `true`, which requires the `admin_key`.
+/// - account: The id of the account to probe, the first account in pool will be used if not set.
+#[derive(Debug, Deserialize)]
+pub struct ReadyQuery {
+    deep: Option<String>,
+    account: Option<i32>,
+}
+
+/// The result of a single readiness check.
+#[derive(Debug, Serialize)]
+struct CheckResult {
+    name: &'static str,
+    ok: bool,
+    message: String,
+}
+
+impl CheckResult {
+    fn new(name: &'static str, result: Result<String, String>) -> Self {
+        match result {
+            Ok(message) => CheckResult { name, ok: true, message },
+            Err(message) => CheckResult { name, ok: false, message },
+        }
+    }
+}
+
+/// The liveness check, which will always return ok as long as the server can
+/// handle the request.
+#[web::get("/healthz")]
+pub async fn healthz() -> impl Responder {
+    HttpResponse::Ok().content_type("text/plain").body("ok")
+}
+
+/// The readiness check, the server is ready when:
+/// - The database can be connected.
+/// - Every configured endpoint has at least one active account in the account pool.
+/// - The config files are loaded cleanly.
+/// - The server is not shutting down.
+/// - (Only with `deep=1`) A minimal probe can be sent through an account, the probe costs a real
+///   request, so it requires the `admin_key` in config as the bearer token.
+#[web::get("/readyz")]
+pub async fn readyz(
+    request: HttpRequest,
+    state: State<(&'static GlobalData, &'static ServerPipeline)>,
+    query: Query<ReadyQuery>,
+) -> impl Responder {
+    let &(data, _) = state.deref();
+    let deep = query.deep.as_deref().is_some_and(|x| x == "1" || x == "true");
+    if deep && let Err(response) = check_admin(&request, data) {
+        return response;
+    }
+
+    let mut checks = vec![
+        CheckResult::new("database", check_database(data).await),
+        CheckResult::new("account_pool", check_account_pool(data)),
+        CheckResult::new("config", check_config(data)),
+        CheckResult::new("shutdown", check_shutdown(data)),
+    ];
+
+    if deep {
+        checks.push(CheckResult::new("probe", probe_account(data, query.account).await));
+    }
+
+    let ready = checks.iter().all(|x| x.ok);
+    let body = serde_json::json!({
+        "status": if ready { "ready" } else { "not_ready" },
+        "checks": checks,
+    });
+
+    if ready {
+        HttpResponse::Ok().json(&body)
+    } else {
+        error!("Readiness check failed: {}", body);
+        HttpResponse::ServiceUnavailable().json(&body)
+    }
+}
+
+async fn check_database(data: &GlobalData) -> Result<String, String> {
+    let mut connection = data
+        .data_base
+        .acquire()
+        .await
+        .map_err(|e| format!("Unable to acquire a connection: {}", e))?;
+
+    connection
+        .ping()
+        .await
+        .map_err(|e| format!("Unable to ping the database: {}", e))?;
+
+    Ok(format!("{} connections in pool", data.data_base.size()))
+}
+
+fn check_account_pool(data: &GlobalData) -> Result<String, String> {
+    let config = data.config.read();
+    let pool = data.account_pool.load();
+
+    let missing = config
+        .endpoint
+        .keys()
+        .filter(|endpoint| !pool.iter().any(|x| x.get_endpoint() == *endpoint))
+        .map(|endpoint| endpoint.to_string())
+        .collect::<Vec<_>>();
+
+    if missing.is_empty() {
+        Ok(format!("{} active accounts in pool", pool.len()))
+    } else {
+        Err(format!("No active account for endpoint: {}", missing.join(", ")))
+    }
+}
+
+fn check_config(data: &GlobalData) -> Result<String, String> {
+    match data.config_error.read().deref() {
+        None => Ok("Config loaded cleanly".to_string()),
+        Some(error) => Err(error.clone()),
+    }
+}
+
+fn check_shutdown(data: &GlobalData) -> Result<String, String> {
+    if data.request_tracker.is_closed() {
+        Err(format!(
+            "Server is shutting down, {} requests running",
+            data.request_tracker.running()
+        ))
+    } else {
+        Ok(format!("{} requests running", data.request_tracker.running()))
+    }
+}
+
+/// Send a minimal request through the account, the response will be dropped.
+/// The probe takes a free slot of the account like a request does, so it never pushes a busy
+/// account over its concurrency limits.
+async fn probe_account(data: &GlobalData, account_id: Option<i32>) -> Result<String, String> {
+    let pool = data.account_pool.load_full();
+    let candidates = pool
+        .iter()
+        .filter(|x| account_id.is_none_or(|id| x.get_account_id() == id))
+        .map(|x| x.get_visitor())
+        .collect::<Vec<_>>();
+    if candidates.is_empty() {
+        return Err("No account can be probed".to_string());
+    }
+
+    let (account, model) = candidates
+        .iter()
+        .find_map(|candidate| {
+            let model = data.model_info.read().any_model(&candidate.endpoint)?.to_string();
+            let account = pool.get_preferred_object(&model, |x| x.account_id == candidate.account_id, false)?;
+            Some((account, model))
+        })
+        .ok_or_else(|| "No free account with an available model can be probed".to_string())?;
+
+    let (sender, _receiver) = channel::<Bytes>(10);
+    let request = OpenAIRequest {
+        model: model.clone(),
+        messages: vec![Message {
+            role: "user".to_string(),
+            content: MessageContent::Common("ping".to_string()),
+        }],
+        stream: Some(false),
+        max_tokens: Some(1),
+        ..Default::default()
+    };
+    let mut sender = ClientSender::new(sender, request, format!("probe-{}", Uuid::new_v4()));
+
+    let start = Instant::now();
+    account
+        .responder
+        .make_response(&mut sender, *account)
+        .await
+        .map_err(|e| format!("Account {} probe failed: {}", account.account_id, e))?;
+
+    Ok(format!(
+        "Account {} answered {} in {}ms",
+        account.account_id,
+        model,
+        start.elapsed().as_millis()
+    ))
+}
@@ -1,5 +1,9 @@
 //! This module contains the main server logic and the enum for the response.
 //! This app use the axum framework to handle the http request and response.
 
+pub mod admin;
+mod admin_auth;
 mod enum_response;
+pub mod health;
+pub mod models;
 pub mod server;
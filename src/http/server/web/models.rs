@@ -0,0 +1,75 @@
+use std::ops::Deref;
+
+use hashbrown::HashSet;
+use ntex::web;
+use ntex::web::types::State;
+use ntex::web::{HttpRequest, HttpResponse, Responder};
+use serde_json::json;
+
+use crate::data::config::entity::runtime_data::{GlobalData, ServerPipeline};
+use crate::http::server::pre_handler::userid_handler::find_key_owner_checked;
+
+/// The model list handler
+/// This handler will list the models in the OpenAI format, only the models that
+/// the caller is allowed to use and have a price will be listed.
+#[web::get("/v1/models")]
+pub async fn list_models(
+    request: HttpRequest,
+    state: State<(&'static GlobalData, &'static ServerPipeline)>,
+) -> impl Responder {
+    let &(data, _) = state.deref();
+
+    let Some(key) = request
+        .headers()
+        .get("Authorization")
+        .and_then(|x| x.to_str().ok())
+        .and_then(|x| x.strip_prefix("Bearer "))
+    else {
+        return error_response(HttpResponse::Unauthorized(), "未找到KEY，请在您的客户端中设置KEY");
+    };
+
+    let allow_list = match find_key_owner_checked(data, key).await {
+        Ok(owner) => owner.allow_list,
+        Err(e) => return error_response(HttpResponse::Unauthorized(), &e.to_string()),
+    };
+
+    let mapped = data
+        .model_mapping
+        .read()
+        .values()
+        .flat_map(|x| x.values().cloned())
+        .collect::<HashSet<_>>();
+    let model_price = data.model_price.read();
+
+    let mut models = data
+        .model_info
+        .read()
+        .list_models()
+        .into_iter()
+        .filter(|(model, _)| model_price.contains_key(*model) && !mapped.contains(*model))
+        .filter(|(model, _)| allow_list.allows(model))
+        .map(|(model, endpoint)| {
+            json!({
+                "id": model,
+                "object": "model",
+                "created": 0,
+                "owned_by": endpoint.to_string(),
+            })
+        })
+        .collect::<Vec<_>>();
+    models.sort_by(|a, b| a["id"].as_str().cmp(&b["id"].as_str()));
+
+    HttpResponse::Ok().json(&json!({
+        "object": "list",
+        "data": models,
+    }))
+}
+
+fn error_response(mut builder: ntex::http::ResponseBuilder, message: &str) -> ntex::http::Response {
+    builder.json(&json!({
+        "error": {
+            "message": message,
+            "type": "invalid_request_error",
+        }
+    }))
+}
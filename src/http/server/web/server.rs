@@ -2,17 +2,21 @@ use log::info;
 use ntex::util::Bytes;
 use ntex::web;
 use ntex::web::types::{Json, State};
-use ntex::web::{HttpRequest, Responder};
+use ntex::web::{HttpRequest, HttpResponse, Responder};
 use std::ops::Deref;
 use std::sync::Arc;
 use tokio::spawn;
 use tokio::sync::mpsc::channel;
+use uuid::Uuid;
 
 use crate::data::config::entity::runtime_data::ServerPipeline;
+use crate::data::config::entity::model_access::ModelAllowList;
 use crate::data::http_api::openai::openai_request::OpenAIRequest;
 use crate::http::client::client_sender::channel_manager::{ChannelSender, ClientSender};
 use crate::http::server::after_handler::ClientEndContext;
 use crate::http::server::pre_handler::ClientJoinContext;
+use crate::http::metrics::{gather_metrics, model_label, REQUESTS_TOTAL};
+use crate::http::server::web::admin_auth::check_admin;
 use crate::http::server::web::enum_response::end;
 use crate::GlobalData;
 
@@ -35,6 +39,11 @@ use crate::GlobalData;
 /// -  The after-handler pipeline running in the async mode, which means
 ///    that any of them can run concurrently, but they can't modify the
 ///    request data and can't stop the request.
+/// - The spawned request task, including the after-handlers, is tracked
+///   by the request tracker, so that a graceful shutdown can wait for it.
+/// - Every request has a request id, which is taken from the `X-Request-Id`
+///   header or generated, it is attached to every log line of the request
+///   and returned in the `X-Request-Id` response header.
 /// # Parameters
 /// - headers: The request headers
 /// - data: The global data
@@ -50,31 +59,48 @@ pub async fn main_chat(
     client_request: Json<OpenAIRequest>,
 ) -> impl Responder {
     let &(data, pipeline) = state.deref();
+    let request_id = get_request_id(&request);
+    let Some(request_guard) = data.request_tracker.track() else {
+        info!("[{}] Server is shutting down, request rejected.", request_id);
+        return HttpResponse::ServiceUnavailable()
+            .header("X-Request-Id", request_id)
+            .body("Server is shutting down");
+    };
+
     let (sender, receiver) = channel::<Bytes>(10);
-    let sender = ClientSender::new(sender, client_request.into_inner());
+    let sender = ClientSender::new(sender, client_request.into_inner(), request_id.clone());
 
     let pre_handler_context = ClientJoinContext {
         sender,
         user_key: None,
         user_id: None,
+        priority: 0,
+        allow_list: ModelAllowList::default(),
         request_header: &request.head().headers,
         global_data: data,
     };
 
     let client_request = pipeline.pre_handler.client_join(pre_handler_context).await;
     if client_request.sender.stopped {
+        let outcome = if client_request.sender.has_error() { "rejected" } else { "command" };
+        REQUESTS_TOTAL
+            .with_label_values(&[model_label(data, &client_request.sender.request.model), "none", outcome])
+            .inc();
+
         client_request.sender.send_error().await.unwrap();
-        return end(receiver, client_request.sender.is_stream()).await;
+        return end(receiver, client_request.sender.is_stream(), &request_id).await;
     }
 
     let user_id = client_request.user_id.clone().unwrap();
+    let priority = client_request.priority;
     let mut sender = client_request.sender;
     let is_stream = sender.request.stream.unwrap_or(false);
 
-    info!("User {} start request......", user_id);
+    info!("[{}] User {} start request......", request_id, user_id);
 
+    let task_request_id = request_id.clone();
     spawn(async move {
-        if let Some(response_data) = data.try_request(&mut sender).await {
+        if let Some(response_data) = data.try_request(&mut sender, user_id, priority).await {
             let after_context = ClientEndContext {
                 sender,
                 response_data,
@@ -97,8 +123,48 @@ pub async fn main_chat(
             }
         }
 
-        info!("End of the request: Done.");
+        info!("[{}] End of the request: Done.", task_request_id);
+        drop(request_guard);
     });
 
-    end(receiver, is_stream).await
-}
\ No newline at end of file
+    end(receiver, is_stream, &request_id).await
+}
+
+/// Get the request id from the `X-Request-Id` header, if the header is missing
+/// or not a valid id, a new one will be generated.
+fn get_request_id(request: &HttpRequest) -> String {
+    request
+        .headers()
+        .get("X-Request-Id")
+        .and_then(|id| id.to_str().ok())
+        .filter(|id| {
+            !id.is_empty()
+                && id.len() <= 128
+                && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+        .map(|id| id.to_string())
+        .unwrap_or_else(|| Uuid::new_v4().to_string())
+}
+
+/// The metrics handler
+/// This handler will export all the metrics in the prometheus text format.
+/// The metrics contain the data of each account, so it requires the `admin_key` in config
+/// as the bearer token, and it is not found if the `admin_key` is not set.
+#[web::get("/metrics")]
+pub async fn metrics(
+    request: HttpRequest,
+    state: State<(&'static GlobalData, &'static ServerPipeline)>,
+) -> impl Responder {
+    let &(data, _) = state.deref();
+    if let Err(response) = check_admin(&request, data) {
+        return response;
+    }
+
+    match gather_metrics(data) {
+        Ok(metrics) => HttpResponse::Ok()
+            .content_type("text/plain; version=0.0.4")
+            .body(metrics),
+        Err(err) => HttpResponse::InternalServerError()
+            .body(format!("Error when gather metrics: {}", err)),
+    }
+}
@@ -6,19 +6,34 @@
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
 use crate::commandline::hot_reload::enable_config_hot_reload;
+use crate::commandline::log_format::JsonLogFormat;
+use crate::data::config::entity::config_file::{Config, LogFormat};
 use crate::data::config::config_helper::get_config;
+use crate::data::database::account_secret::MasterKey;
+use crate::data::database::auth_cache::AuthCache;
+use crate::data::database::change_listener::listen_database_changes;
 use crate::data::database::database_manager::connect_to_database_sqlx;
-use crate::http::client::util::account_manager::load_account_from_database;
+use crate::http::client::util::account_manager::{load_account_from_database, schedule_budget_reset};
+use crate::http::client::util::account_tester::schedule_account_test;
+use crate::http::client::util::affinity::schedule_affinity_eviction;
+use crate::http::client::util::affinity::AffinityMap;
 use crate::http::client::util::counter::concurrency_pool::VecSafePool;
-use crate::http::server::web::server::main_chat;
+use crate::http::client::util::request_queue::RequestQueue;
+use crate::http::server::shutdown::RequestTracker;
+use crate::http::server::web::admin::test_account;
+use crate::http::server::web::health::{healthz, readyz};
+use crate::http::server::web::models::list_models;
+use crate::http::server::web::server::{main_chat, metrics};
 use crate::http::server::{get_client_end_handler, get_client_join_handler};
 use anyhow::anyhow;
+use arc_swap::ArcSwap;
 use data::config::entity::model_price::ModelPriceMap;
+use data::config::entity::model_cost::ModelCostMap;
 use data::config::entity::runtime_data::{GlobalData, ServerPipeline};
 use fast_log::consts::LogSize;
 use fast_log::plugin::file_split::{KeepType, Rolling, RollingType};
 use fast_log::plugin::packer::LZ4Packer;
-use log::info;
+use log::{info, warn};
 use ntex::web::middleware::Compress;
 use ntex::web::{server, App};
 use ntex_cors::Cors;
@@ -31,7 +46,9 @@ use std::io::BufReader;
 use std::net::IpAddr;
 use std::path::Path;
 use std::str::FromStr;
+use std::time::Duration;
 use ntex::web::types::JsonConfig;
+use tokio::spawn;
 use tokio::task::spawn_blocking;
 use data::config::entity::model_mapping::ModelMapping;
 use crate::data::config::entity::model_manager::ModelManager;
@@ -40,8 +57,8 @@ mod data;
 mod http;
 mod commandline;
 
-fn enable_logging() {
-    let config = fast_log::config::Config::new()
+fn enable_logging(config: &Config) {
+    let log_config = fast_log::config::Config::new()
         .level(log::LevelFilter::Info)
         .console()
         .chan_len(Some(100000))
@@ -51,39 +68,53 @@ fn enable_logging() {
             KeepType::All,
             LZ4Packer {},
         );
-    fast_log::init(config).unwrap();
+
+    let log_config = match config.log_format {
+        LogFormat::Text => log_config,
+        LogFormat::Json => log_config.format(JsonLogFormat),
+    };
+    fast_log::init(log_config).unwrap();
 }
 
 #[ntex::main]
 async fn main() -> anyhow::Result<()> {
     color_eyre::install().unwrap();
-    enable_logging();
+
+    // Load config from file
+    let config = get_config().expect("Error loading config");
+    enable_logging(&config);
     aws_lc_rs::default_provider().install_default().expect("Error installing default rustls provider");
 
     let data: &'static GlobalData = {
-        // Load config from file
-        let config = get_config().expect("Error loading config");
-
         // Load model price from file
         let price_map = ModelPriceMap::new(&config)?;
+        let cost_map = ModelCostMap::new(&config)?;
         let model_mapping = ModelMapping::new(&config)?;
 
         // Connect to database
         let db = connect_to_database_sqlx(&config).await.expect("Error connecting to database");
 
         // Load account from database
-        let account = load_account_from_database(&config, &db).await?;
+        let master_key = MasterKey::load()?;
+        let account = load_account_from_database(&config, &db, &master_key).await?;
         info!("Loaded {} accounts from database.", account.len());
         
         let model_info = ModelManager::new(&config).expect("Error loading model info");
 
         let data = GlobalData {
             data_base: db,
-            account_pool: RwLock::new(account.to_vec_safe_pool(config.request_concurrency_count)),
+            account_pool: ArcSwap::from_pointee(account.to_vec_safe_pool(&config)),
             config: RwLock::new(config),
             model_price: RwLock::new(price_map),
+            model_cost: RwLock::new(cost_map),
             model_mapping: RwLock::new(model_mapping),
             model_info: RwLock::new(model_info),
+            config_error: RwLock::new(None),
+            request_tracker: RequestTracker::default(),
+            master_key: ArcSwap::from_pointee(master_key),
+            auth_cache: AuthCache::default(),
+            request_queue: RequestQueue::default(),
+            affinity: AffinityMap::default(),
         };
 
         Box::leak(Box::new(data))
@@ -97,6 +128,11 @@ async fn main() -> anyhow::Result<()> {
         enable_config_hot_reload(data).unwrap();
     });
 
+    spawn(listen_database_changes(data));
+    spawn(schedule_account_test(data));
+    spawn(schedule_budget_reset(data));
+    spawn(schedule_affinity_eviction(data));
+
     let server_pipeline = ServerPipeline {
         pre_handler: get_client_join_handler(),
         after_handler: get_client_end_handler(),
@@ -120,6 +156,11 @@ async fn main() -> anyhow::Result<()> {
         let json_config = JsonConfig::default().limit(40960000);
         App::new()
             .service(main_chat)
+            .service(list_models)
+            .service(test_account)
+            .service(metrics)
+            .service(healthz)
+            .service(readyz)
             .state(json_config)
             .state((data, server_pipeline))
             .wrap(Compress::default())
@@ -146,7 +187,35 @@ async fn main() -> anyhow::Result<()> {
         server
     };
 
-    server.run().await?;
+    // The signals are handled by ourselves, so that the running requests can be drained
+    // before the workers are stopped.
+    let server = server.disable_signals().run();
+    let handle = server.clone();
+    spawn(async move {
+        data.request_tracker.wait_for_shutdown().await;
+        info!("Server is shutting down, stop accepting new connections.");
+        handle.pause().await;
+
+        let deadline = Duration::from_secs(data.config.read().shutdown_timeout);
+        let running = data.request_tracker.running();
+        info!("Waiting up to {}s for {} running requests.", deadline.as_secs(), running);
+        if data.request_tracker.drain(deadline).await {
+            info!("All requests are finished.");
+        } else {
+            warn!(
+                "Shutdown timeout, {} requests are still running.",
+                data.request_tracker.running()
+            );
+        }
+
+        handle.stop(true).await;
+    });
+
+    server.await?;
+    info!("Server stopped.");
+    log::logger().flush();
 
-    Ok(())
+    // The command listener and the hot reload are blocking on their threads,
+    // which will never finish by themselves.
+    std::process::exit(0);
 }